@@ -0,0 +1,168 @@
+use sdl2::controller::Axis;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Axes that moved less than this (raw units) while recording are left uncalibrated.
+const MIN_SPAN: i32 = 1_000;
+
+/// Measured rest position and extremes of one axis.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AxisCalibration {
+    #[serde(with = "crate::profile::axis_name")]
+    pub axis: Axis,
+    pub min: i16,
+    pub center: i16,
+    pub max: i16,
+}
+
+impl AxisCalibration {
+    /// Rescale a raw reading so `center` becomes 0 and `min`/`max` reach full throw.
+    pub fn apply(&self, raw: i16) -> i16 {
+        let (raw, center) = (raw as f32, self.center as f32);
+        let span = if raw >= center {
+            self.max as f32 - center
+        } else {
+            center - self.min as f32
+        };
+        let norm = if span > 0.0 {
+            (raw - center) / span
+        } else {
+            0.0
+        };
+        (norm.clamp(-1.0, 1.0) * 32767.0).round() as i16
+    }
+}
+
+/// Calibration for one physical controller, identified by its SDL GUID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceCalibration {
+    pub guid: String,
+    /// Only for the Nth attached controller with this GUID, as in `DeviceProfile`.
+    /// Applies to all of them if omitted.
+    #[serde(default)]
+    pub slot: Option<usize>,
+    #[serde(default)]
+    pub name: String,
+    pub axes: Vec<AxisCalibration>,
+}
+
+impl DeviceCalibration {
+    pub fn axis(&self, axis: Axis) -> Option<&AxisCalibration> {
+        self.axes.iter().find(|c| c.axis == axis)
+    }
+
+    pub fn apply(&self, axis: Axis, raw: i16) -> i16 {
+        self.axis(axis).map_or(raw, |c| c.apply(raw))
+    }
+}
+
+/// Records rest positions and extremes while the calibration wizard runs.
+#[derive(Debug, Default)]
+pub struct CalibrationRecorder {
+    rest: HashMap<Axis, i16>,
+    range: HashMap<Axis, (i16, i16)>,
+}
+
+impl CalibrationRecorder {
+    /// Start recording extremes from the given rest positions.
+    pub fn new(rest: HashMap<Axis, i16>) -> Self {
+        let range = rest.iter().map(|(axis, v)| (*axis, (*v, *v))).collect();
+        Self { rest, range }
+    }
+
+    pub fn record(&mut self, axis: Axis, value: i16) {
+        let center = self.rest.get(&axis).copied().unwrap_or(0);
+        let (min, max) = self.range.entry(axis).or_insert((center, center));
+        *min = (*min).min(value);
+        *max = (*max).max(value);
+    }
+
+    pub fn range(&self, axis: Axis) -> Option<(i16, i16)> {
+        self.range.get(&axis).copied()
+    }
+
+    pub fn finish(&self, guid: &str, slot: usize, name: &str, axes: &[Axis]) -> DeviceCalibration {
+        let axes = axes
+            .iter()
+            .filter_map(|axis| {
+                let center = self.rest.get(axis).copied().unwrap_or(0);
+                let (min, max) = self.range(*axis)?;
+                if (max as i32 - min as i32) < MIN_SPAN {
+                    return None;
+                }
+                Some(AxisCalibration {
+                    axis: *axis,
+                    min,
+                    center,
+                    max,
+                })
+            })
+            .collect();
+        DeviceCalibration {
+            guid: guid.to_string(),
+            slot: Some(slot),
+            name: name.to_string(),
+            axes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn left_x() -> AxisCalibration {
+        AxisCalibration {
+            axis: Axis::LeftX,
+            min: -20_000,
+            center: 1_000,
+            max: 30_000,
+        }
+    }
+
+    #[test]
+    fn center_and_extremes_reach_full_range() {
+        let c = left_x();
+        assert_eq!(c.apply(1_000), 0);
+        assert_eq!(c.apply(30_000), 32767);
+        assert_eq!(c.apply(-20_000), -32767);
+        assert_eq!(c.apply(i16::MAX), 32767);
+        assert_eq!(c.apply(i16::MIN), -32767);
+    }
+
+    #[test]
+    fn each_side_scales_by_its_own_span() {
+        let c = left_x();
+        // Halfway from center to each end
+        assert_eq!(c.apply(15_500), 16384);
+        assert_eq!(c.apply(-9_500), -16384);
+    }
+
+    #[test]
+    fn uncalibrated_axes_pass_through() {
+        let device = DeviceCalibration {
+            guid: "guid".to_string(),
+            slot: None,
+            name: String::new(),
+            axes: vec![left_x()],
+        };
+        assert_eq!(device.apply(Axis::LeftX, 1_000), 0);
+        assert_eq!(device.apply(Axis::RightY, 1_000), 1_000);
+    }
+
+    #[test]
+    fn recorder_skips_axes_that_barely_moved() {
+        let rest = [(Axis::LeftX, 0), (Axis::LeftY, 0)].into_iter().collect();
+        let mut recorder = CalibrationRecorder::new(rest);
+        recorder.record(Axis::LeftX, -30_000);
+        recorder.record(Axis::LeftX, 31_000);
+        recorder.record(Axis::LeftY, 500);
+        let device = recorder.finish("guid", 1, "Pad", &[Axis::LeftX, Axis::LeftY]);
+        assert_eq!(device.slot, Some(1));
+        assert_eq!(device.axes.len(), 1);
+        assert_eq!(
+            device.axis(Axis::LeftX).map(|c| (c.min, c.max)),
+            Some((-30_000, 31_000))
+        );
+    }
+}
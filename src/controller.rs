@@ -1,32 +1,189 @@
 extern crate sdl2;
 
-use crate::events::ControllerEvent;
+use crate::curve::ResponseCurve;
+use crate::events::{ControllerCommand, ControllerEvent, DetectedDevice, DeviceId};
+use crate::keyboard::KeyboardMode;
+use crate::lfo::{LfoConfig, LfoParams};
+use crate::mapping::{
+    AxisBinding, AxisMapper, ButtonAction, ButtonBinding, ButtonMapper, Layer, ModulationTarget,
+    StickBinding, StickMapper,
+};
+use crate::melodic::{MelodicConfig, MelodicShift};
+use crate::motion::{MotionBinding, MotionSource, MotionTracker};
+use crate::pitch::{PitchQuantize, PitchRelease, PitchSmoothing, PitchState};
+use crate::profile::Profile;
+use crate::rumble::{Rumble, RumbleConfig};
+use crate::touchpad::{TouchpadBinding, TouchpadState};
+use crate::velocity::VelocityTracker;
 use sdl2::controller::{Axis, Button, GameController};
 use sdl2::event::Event;
-use std::collections::HashMap;
-use std::sync::mpsc;
+use sdl2::sensor::SensorType;
+use sdl2::{GameControllerSubsystem, JoystickSubsystem};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, TryRecvError};
+use std::time::Instant;
+
+/// How long the event loop blocks on SDL before checking for commands from the UI
+/// and advancing the pitch smoothing.
+const POLL_INTERVAL_MS: u32 = 10;
 
 /// User-configurable mapping for musical actions and axis processing.
-#[derive(Debug, Clone)]
+/// Persisted as a TOML profile (see `profile.rs`); missing fields fall back to the defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ControllerConfig {
-    pub note_button: Button,
+    /// MIDI channel (0-15) used by bindings that don't name their own.
+    pub channel: u8,
+    pub buttons: Vec<ButtonBinding>,
+    /// Axes routed to Control Change, independent of (and in addition to) `pitch_axis`.
+    pub axes: Vec<AxisBinding>,
+    /// Alternative button and axis bindings, switched by modifier buttons.
+    pub layers: Vec<Layer>,
+    /// Scale degrees on the face buttons and octave/key on the D-pad, off if omitted.
+    pub melodic: Option<MelodicConfig>,
+    /// Sticks read as angle and radius rather than as two separate axes.
+    pub sticks: Vec<StickBinding>,
+    /// Tilt and rotation from the pad's motion sensors.
+    pub motion: Vec<MotionBinding>,
+    /// Finger positions on the touchpad (DualShock 4, DualSense).
+    pub touchpad: Vec<TouchpadBinding>,
+    #[serde(with = "crate::profile::axis_name")]
     pub pitch_axis: Axis,
     pub invert_pitch: bool,
+    /// Bipolar stick, one trigger, or both triggers together.
+    pub pitch_axis_mode: PitchAxisMode,
     pub deadzone: i16,
+    /// Applied after the deadzone, before the value becomes a pitch bend.
+    pub pitch_curve: ResponseCurve,
+    /// Low-pass and slew limiting of the bend output, off by default.
+    pub pitch_smoothing: PitchSmoothing,
+    /// Snaps the bend to semitones or scale steps, see `pitch::quantize_bend`.
+    pub pitch_quantize: PitchQuantize,
+    /// Bend range set on the synth, in semitones.
+    pub pitch_bend_range: f32,
+    /// Semitones reached with the stick fully up; `pitch_bend_range` if omitted.
+    pub pitch_bend_up: Option<f32>,
+    /// Semitones reached with the stick fully down; `pitch_bend_range` if omitted.
+    pub pitch_bend_down: Option<f32>,
+    /// Time to glide from one quantized step to the next, in milliseconds.
+    pub pitch_glide_ms: f32,
+    /// Return to center after the stick is let go, off by default.
+    pub pitch_release: PitchRelease,
+    /// Vibrato run by the MIDI worker on `channel`, off if omitted.
+    pub lfo: Option<LfoConfig>,
+    /// Rumble following the bend; pulses on presses are set per button.
+    pub rumble: RumbleConfig,
 }
 
 impl Default for ControllerConfig {
     fn default() -> Self {
         Self {
-            note_button: Button::A,
+            channel: 0,
+            buttons: vec![ButtonBinding {
+                button: Button::A,
+                action: ButtonAction::Note {
+                    note: 60, // Middle C
+                    velocity: 100,
+                    channel: None,
+                },
+                rumble: None,
+                velocity_source: None,
+            }],
+            axes: Vec::new(),
+            layers: Vec::new(),
+            melodic: None,
+            sticks: Vec::new(),
+            motion: Vec::new(),
+            touchpad: Vec::new(),
             pitch_axis: Axis::LeftY,
             invert_pitch: true, // LeftY is inverted (up = negative) on most controllers
-            deadzone: 2_000,    // small default deadzone to mask minor drift
+            pitch_axis_mode: PitchAxisMode::Bipolar,
+            deadzone: 2_000, // small default deadzone to mask minor drift
+            pitch_curve: ResponseCurve::Linear,
+            pitch_smoothing: PitchSmoothing::default(),
+            pitch_quantize: PitchQuantize::Off,
+            pitch_bend_range: 2.0, // the General MIDI default
+            pitch_bend_up: None,
+            pitch_bend_down: None,
+            pitch_glide_ms: 0.0,
+            pitch_release: PitchRelease::default(),
+            lfo: None,
+            rumble: RumbleConfig::default(),
         }
     }
 }
 
-fn normalize_axis(raw: i16, invert: bool) -> f32 {
+/// How the pitch axis turns into a bend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PitchAxisMode {
+    /// Center is no bend, the ends bend down and up (`invert_pitch` flips them).
+    #[default]
+    Bipolar,
+    /// For triggers: released is no bend, fully pressed bends all the way `direction`.
+    Unipolar { direction: BendDirection },
+    /// Both triggers make one bend, left pulls down and right pushes up.
+    /// `pitch_axis` is ignored.
+    Triggers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BendDirection {
+    #[default]
+    Up,
+    Down,
+}
+
+impl ControllerConfig {
+    /// Whether motion on `axis` changes the pitch input. A stick, motion or touchpad
+    /// binding driving the pitch takes over from `pitch_axis`.
+    pub fn is_pitch_axis(&self, axis: Axis) -> bool {
+        let axis_matches = match self.pitch_axis_mode {
+            PitchAxisMode::Bipolar | PitchAxisMode::Unipolar { .. } => axis == self.pitch_axis,
+            PitchAxisMode::Triggers => matches!(axis, Axis::TriggerLeft | Axis::TriggerRight),
+        };
+        axis_matches
+            && !self.stick_drives_pitch()
+            && !self.motion_drives_pitch()
+            && !self.touchpad_drives_pitch()
+    }
+
+    /// Bindings of `button` on `layer`, falling back to the base ones if the layer
+    /// doesn't bind it.
+    pub fn button_bindings(&self, layer: Option<usize>, button: Button) -> Vec<&ButtonBinding> {
+        let layered = layer
+            .and_then(|i| self.layers.get(i))
+            .map(|l| &l.buttons[..]);
+        layered_bindings(&self.buttons, layered, |b| b.button == button)
+    }
+
+    /// Bindings of `axis` on `layer`, falling back to the base ones if the layer
+    /// doesn't bind it.
+    pub fn axis_bindings(&self, layer: Option<usize>, axis: Axis) -> Vec<&AxisBinding> {
+        let layered = layer.and_then(|i| self.layers.get(i)).map(|l| &l.axes[..]);
+        layered_bindings(&self.axes, layered, |b| b.axis == axis)
+    }
+
+    pub fn motion_drives_pitch(&self) -> bool {
+        self.motion
+            .iter()
+            .any(|m| m.target == ModulationTarget::PitchBend)
+    }
+
+    pub fn touchpad_drives_pitch(&self) -> bool {
+        self.touchpad
+            .iter()
+            .any(|t| t.target == ModulationTarget::PitchBend)
+    }
+
+    pub fn stick_drives_pitch(&self) -> bool {
+        self.sticks.iter().any(|s| s.drives_pitch())
+    }
+}
+
+pub(crate) fn normalize_axis(raw: i16, invert: bool) -> f32 {
     // Promote to i32 and clamp to symmetric range to avoid -32768 overflow/asymmetry
     let clamped = (raw as i32).clamp(-32767, 32767) as f32;
     let mut norm = (clamped / 32767.0).clamp(-1.0, 1.0); // now in [-1.0, 1.0]
@@ -36,7 +193,54 @@ fn normalize_axis(raw: i16, invert: bool) -> f32 {
     norm
 }
 
-fn apply_deadzone(norm: f32, deadzone: f32) -> f32 {
+/// The layer's bindings matching `matches`, or the base ones if it has none.
+fn layered_bindings<'a, T>(
+    base: &'a [T],
+    layer: Option<&'a [T]>,
+    matches: impl Fn(&T) -> bool,
+) -> Vec<&'a T> {
+    let on_layer: Vec<_> = layer
+        .unwrap_or_default()
+        .iter()
+        .filter(|b| matches(b))
+        .collect();
+    if on_layer.is_empty() {
+        base.iter().filter(|b| matches(b)).collect()
+    } else {
+        on_layer
+    }
+}
+
+/// Trigger reading to `0.0..=1.0`; anything below rest counts as released.
+pub(crate) fn normalize_unipolar(raw: i16) -> f32 {
+    (raw.max(0) as f32 / 32767.0).clamp(0.0, 1.0)
+}
+
+/// The pitch input in `-1.0..=1.0` before the curve, from the latest axis values.
+pub(crate) fn pitch_input(config: &ControllerConfig, axes: &HashMap<Axis, i16>) -> f32 {
+    let deadzone = (config.deadzone as f32) / 32767.0;
+    let value = |axis: Axis| axes.get(&axis).copied().unwrap_or(0);
+    match config.pitch_axis_mode {
+        PitchAxisMode::Bipolar => {
+            let norm = normalize_axis(value(config.pitch_axis), config.invert_pitch);
+            apply_deadzone(norm, deadzone)
+        }
+        PitchAxisMode::Unipolar { direction } => {
+            let norm = apply_deadzone(normalize_unipolar(value(config.pitch_axis)), deadzone);
+            match direction {
+                BendDirection::Up => norm,
+                BendDirection::Down => -norm,
+            }
+        }
+        PitchAxisMode::Triggers => {
+            let down = apply_deadzone(normalize_unipolar(value(Axis::TriggerLeft)), deadzone);
+            let up = apply_deadzone(normalize_unipolar(value(Axis::TriggerRight)), deadzone);
+            up - down
+        }
+    }
+}
+
+pub(crate) fn apply_deadzone(norm: f32, deadzone: f32) -> f32 {
     if norm.abs() < deadzone {
         0.0
     } else {
@@ -47,13 +251,7 @@ fn apply_deadzone(norm: f32, deadzone: f32) -> f32 {
     }
 }
 
-fn pitch_bend_from_norm(norm: f32) -> u16 {
-    // Map [-1.0, 1.0] to [0, 16383] with center 8192
-    let v = ((norm + 1.0) * 8191.5).round();
-    v.clamp(0.0, 16383.0) as u16
-}
-
-fn known_buttons() -> Vec<Button> {
+pub(crate) fn known_buttons() -> Vec<Button> {
     vec![
         Button::A,
         Button::B,
@@ -79,7 +277,7 @@ fn known_buttons() -> Vec<Button> {
     ]
 }
 
-fn known_axes() -> Vec<Axis> {
+pub(crate) fn known_axes() -> Vec<Axis> {
     vec![
         Axis::LeftX,
         Axis::LeftY,
@@ -97,14 +295,153 @@ fn collect_present_inputs(_controller: &GameController) -> (Vec<Button>, Vec<Axi
     (known_buttons(), known_axes())
 }
 
+/// An opened controller and its per-device mapping state.
+struct OpenController {
+    controller: GameController,
+    guid: String,
+    /// Position among attached controllers with the same GUID, see `DeviceProfile::slot`.
+    slot: usize,
+    buttons: ButtonMapper,
+    axes: AxisMapper,
+    sticks: StickMapper,
+    pitch: PitchState,
+    /// Latest calibrated value of every axis, for the LFO's depth and rate.
+    axis_values: HashMap<Axis, i16>,
+    /// Channel and settings of the LFO last sent to the MIDI worker.
+    lfo_sent: Option<(u8, LfoParams)>,
+    /// Bend hold state last shown in the UI.
+    hold_sent: bool,
+    /// Active layer last shown in the UI.
+    layer_sent: Option<usize>,
+    /// Melodic mode octave and key last shown in the UI.
+    melodic_sent: MelodicShift,
+    motion: MotionTracker,
+    /// Whole degrees of each motion source last shown in the UI.
+    motion_shown: HashMap<MotionSource, i32>,
+    touchpad: TouchpadState,
+    rumble: Rumble,
+    velocities: VelocityTracker,
+}
+
+impl OpenController {
+    /// Tell the MIDI worker about LFO changes, including a channel change or the LFO
+    /// being turned off.
+    fn send_lfo(&mut self, tx: &mpsc::Sender<ControllerEvent>, config: &ControllerConfig) {
+        let params = config.lfo.as_ref().map(|lfo| {
+            (
+                config.channel,
+                lfo.params(&self.axis_values, config.pitch_bend_range),
+            )
+        });
+        if params == self.lfo_sent {
+            return;
+        }
+        if let Some((channel, _)) = self.lfo_sent {
+            if params.map(|(c, _)| c) != Some(channel) {
+                let _ = tx.send(ControllerEvent::Lfo {
+                    channel,
+                    params: None,
+                });
+            }
+        }
+        if let Some((channel, params)) = params {
+            let _ = tx.send(ControllerEvent::Lfo {
+                channel,
+                params: Some(params),
+            });
+        }
+        self.lfo_sent = params;
+    }
+
+    /// Advance the pitch pipeline by `dt_ms` and send the bend if it changed.
+    fn send_pitch(
+        &mut self,
+        tx: &mpsc::Sender<ControllerEvent>,
+        config: &ControllerConfig,
+        dt_ms: f32,
+    ) {
+        if let Some(value) = self.pitch.tick(config, &self.buttons, dt_ms) {
+            let _ = tx.send(ControllerEvent::PitchBend {
+                channel: config.channel,
+                value,
+            });
+        }
+        if self.pitch.is_frozen() != self.hold_sent {
+            self.hold_sent = self.pitch.is_frozen();
+            let _ = tx.send(ControllerEvent::BendHold {
+                device: self.controller.instance_id(),
+                held: self.hold_sent,
+            });
+        }
+    }
+
+    fn send_layer(&mut self, tx: &mpsc::Sender<ControllerEvent>) {
+        let layer = self.buttons.active_layer();
+        if layer != self.layer_sent {
+            self.layer_sent = layer;
+            let _ = tx.send(ControllerEvent::Layer {
+                device: self.controller.instance_id(),
+                layer,
+            });
+        }
+    }
+
+    fn send_melodic(&mut self, tx: &mpsc::Sender<ControllerEvent>) {
+        let shift = self.buttons.melodic_shift();
+        if shift != self.melodic_sent {
+            self.melodic_sent = shift;
+            let _ = tx.send(ControllerEvent::Melodic {
+                device: self.controller.instance_id(),
+                shift,
+            });
+        }
+    }
+
+    /// Read the touchpad, which SDL only offers as state, and map any change.
+    fn poll_touchpad(&mut self, tx: &mpsc::Sender<ControllerEvent>, config: &ControllerConfig) {
+        if !self.touchpad.poll(&self.controller) {
+            return;
+        }
+        let _ = tx.send(ControllerEvent::RawTouch {
+            device: self.controller.instance_id(),
+            fingers: self.touchpad.fingers().to_vec(),
+        });
+        let (events, pitch) = self.touchpad.map(config);
+        for event in events {
+            let _ = tx.send(event);
+        }
+        if let Some((norm, resting)) = pitch {
+            self.pitch
+                .set_input(config.pitch_curve.apply(norm), resting);
+        }
+    }
+
+    /// Start pulses asked for since the last call and follow the bend amount.
+    fn update_rumble(&mut self, config: &ControllerConfig, now: Instant) {
+        if let Some(pulse) = self.buttons.take_pulse() {
+            self.rumble.pulse(&pulse, now);
+        }
+        if self.pitch.take_step() {
+            if let Some(tick) = &config.rumble.step_tick {
+                self.rumble.pulse(tick, now);
+            }
+        }
+        self.rumble
+            .set_continuous(config.rumble.bend_strength * self.pitch.output().abs());
+        self.rumble.update(&mut self.controller, now);
+    }
+}
+
 pub fn start_controller(
     tx: mpsc::Sender<ControllerEvent>,
-    config: ControllerConfig,
+    commands: mpsc::Receiver<ControllerCommand>,
+    mut profile: Profile,
 ) -> Result<(), String> {
     // Required for certain controllers to work on Windows
     sdl2::hint::set("SDL_JOYSTICK_THREAD", "1");
 
     let sdl_context = sdl2::init()?;
+    let joystick_subsystem = sdl_context.joystick()?;
     let game_controller_subsystem = sdl_context.game_controller()?;
 
     let available = game_controller_subsystem
@@ -113,91 +450,180 @@ pub fn start_controller(
 
     println!("{} joysticks available", available);
 
-    // Find and open the first available game controller
-    let controller = (0..available)
-        .find_map(|id| {
-            if !game_controller_subsystem.is_game_controller(id) {
-                println!("{} is not a game controller", id);
-                return None;
+    // Open every enabled game controller. If there are none we keep running and
+    // pick them up from ControllerDeviceAdded later.
+    let mut controllers = Controllers {
+        game_controller_subsystem,
+        joystick_subsystem,
+        tx: tx.clone(),
+        open: HashMap::new(),
+    };
+    controllers.sync(&profile);
+    if controllers.open.is_empty() {
+        println!("No controller found, waiting for one to be connected");
+        if profile.keyboard.mode != KeyboardMode::Off {
+            println!("Until then the computer keyboard plays (see the monitor window)");
+        }
+    }
+
+    // Main event loop
+    let mut event_pump = sdl_context.event_pump()?;
+    let mut last_tick = Instant::now();
+    'events: loop {
+        loop {
+            match commands.try_recv() {
+                Ok(ControllerCommand::UpdateProfile(new_profile)) => {
+                    profile = new_profile;
+                    // The set of enabled controllers may have changed
+                    controllers.sync(&profile);
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => break 'events,
             }
+        }
 
-            println!("Attempting to open controller {}", id);
+        let now = Instant::now();
+        controllers.tick(
+            &profile,
+            now.duration_since(last_tick).as_secs_f32() * 1000.0,
+        );
+        last_tick = now;
 
-            match game_controller_subsystem.open(id) {
-                Ok(c) => {
-                    println!("Success: opened \"{}\"", c.name());
-                    Some(c)
+        let Some(event) = event_pump.wait_event_timeout(POLL_INTERVAL_MS) else {
+            continue;
+        };
+        match event {
+            Event::ControllerButtonDown { which, button, .. } => {
+                let Some(dev) = controllers.open.get_mut(&which) else {
+                    continue;
+                };
+                let config = profile.config_for(&dev.guid, dev.slot);
+                let _ = tx.send(ControllerEvent::RawButton {
+                    device: which,
+                    button,
+                    pressed: true,
+                });
+
+                for event in dev.buttons.press(config, button, &dev.velocities) {
+                    let _ = tx.send(event);
+                }
+                dev.send_layer(&tx);
+                dev.send_melodic(&tx);
+                if dev.buttons.take_recenter() {
+                    dev.motion.recenter();
                 }
-                Err(e) => {
-                    println!("failed: {:?}", e);
-                    None
+                // Resetting through the pitch state keeps smoothing from bending it back
+                if dev.buttons.take_bend_reset() {
+                    dev.pitch.reset();
                 }
+                // Scale quantization follows the held note, and holds freeze the bend
+                dev.send_pitch(&tx, config, 0.0);
             }
-        });
+            Event::ControllerButtonUp { which, button, .. } => {
+                let Some(dev) = controllers.open.get_mut(&which) else {
+                    continue;
+                };
+                let _ = tx.send(ControllerEvent::RawButton {
+                    device: which,
+                    button,
+                    pressed: false,
+                });
 
-    // If no controller is found, return
-    let controller = match controller {
-        Some(c) => c,
-        None => {
-            println!("No controller found, MIDI will still play without controller input");
-            return Ok(());
-        }
-    };
-
-    println!("Controller mapping: {}", controller.mapping());
-    println!("Configured note button: {:?}", config.note_button);
-    println!("Configured pitch axis: {:?}", config.pitch_axis);
+                for event in dev.buttons.release(button) {
+                    let _ = tx.send(event);
+                }
+                dev.send_layer(&tx);
+                // Releasing a hold button returns to the stick
+                let config = profile.config_for(&dev.guid, dev.slot);
+                dev.send_pitch(&tx, config, 0.0);
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } => {
+                let Some(dev) = controllers.open.get_mut(&which) else {
+                    continue;
+                };
+                let config = profile.config_for(&dev.guid, dev.slot);
+                let _ = tx.send(ControllerEvent::RawAxis {
+                    device: which,
+                    axis,
+                    value,
+                });
 
-    let (present_buttons, present_axes) = collect_present_inputs(&controller);
-    let _ = tx.send(ControllerEvent::ControllerInfo {
-        name: controller.name(),
-        mapping: controller.mapping(),
-        buttons: present_buttons.clone(),
-        axes: present_axes.clone(),
-    });
+                let value = profile
+                    .calibration_for(&dev.guid, dev.slot)
+                    .map_or(value, |c| c.apply(axis, value));
 
-    // Track last raw states to avoid spamming identical events to UI
-    let mut button_state: HashMap<Button, bool> = HashMap::new();
-    let mut axis_state: HashMap<Axis, i16> = HashMap::new();
+                let layer = dev.buttons.active_layer();
+                for event in dev.axes.motion(config, layer, axis, value) {
+                    let _ = tx.send(event);
+                }
+                dev.axis_values.insert(axis, value);
+                dev.velocities.axis(axis, value, Instant::now());
+                dev.send_lfo(&tx, config);
 
-    // Main event loop
-    for event in sdl_context.event_pump()?.wait_iter() {
-        match event {
-            Event::ControllerButtonDown { button, .. } => {
-                button_state.insert(button, true);
-                let _ = tx.send(ControllerEvent::RawButton { button, pressed: true });
+                let (events, stick_pitch) = dev.sticks.motion(config, axis, value);
+                for event in events {
+                    let _ = tx.send(event);
+                }
 
-                if button == config.note_button {
-                    println!("Button {:?} pressed - sending MIDI note on", button);
-                    let _ = tx.send(ControllerEvent::ButtonDown);
+                let pitch = match stick_pitch {
+                    Some((norm, resting)) => Some((config.pitch_curve.apply(norm), resting)),
+                    None if config.is_pitch_axis(axis) => {
+                        let norm = pitch_input(config, &dev.axis_values);
+                        Some((config.pitch_curve.apply(norm), norm == 0.0))
+                    }
+                    None => None,
+                };
+                if let Some((norm, resting)) = pitch {
+                    dev.pitch.set_input(norm, resting);
+                    // Send right away rather than on the next tick; with smoothing or
+                    // glide a zero-length tick doesn't move the output
+                    dev.send_pitch(&tx, config, 0.0);
                 }
             }
-            Event::ControllerButtonUp { button, .. } => {
-                button_state.insert(button, false);
-                let _ = tx.send(ControllerEvent::RawButton { button, pressed: false });
+            Event::ControllerSensorUpdated {
+                which,
+                sensor,
+                data,
+                ..
+            } => {
+                let Some(dev) = controllers.open.get_mut(&which) else {
+                    continue;
+                };
+                let config = profile.config_for(&dev.guid, dev.slot);
+                dev.motion.sensor(sensor, data, Instant::now());
+                // Sensors report hundreds of times a second, only show whole degrees
+                for source in MotionSource::all() {
+                    let Some(degrees) = dev.motion.angle(source) else {
+                        continue;
+                    };
+                    if dev.motion_shown.insert(source, degrees.round() as i32)
+                        != Some(degrees.round() as i32)
+                    {
+                        let _ = tx.send(ControllerEvent::RawMotion {
+                            device: which,
+                            source,
+                            degrees,
+                        });
+                    }
+                }
 
-                if button == config.note_button {
-                    println!("Button {:?} released - sending MIDI note off", button);
-                    let _ = tx.send(ControllerEvent::ButtonUp);
+                let (events, pitch) = dev.motion.map(config);
+                for event in events {
+                    let _ = tx.send(event);
                 }
-            }
-            Event::ControllerAxisMotion { axis, value, .. } => {
-                axis_state.insert(axis, value);
-                let _ = tx.send(ControllerEvent::RawAxis { axis, value });
-
-                if axis == config.pitch_axis {
-                    let mut norm = normalize_axis(value, config.invert_pitch);
-                    norm = apply_deadzone(norm, (config.deadzone as f32) / 32767.0);
-                    let pitch_bend_value = pitch_bend_from_norm(norm);
-                    let _ = tx.send(ControllerEvent::PitchBend(pitch_bend_value));
+                if let Some((norm, resting)) = pitch {
+                    dev.pitch.set_input(config.pitch_curve.apply(norm), resting);
+                    dev.send_pitch(&tx, config, 0.0);
                 }
             }
-            Event::ControllerDeviceAdded { which, .. } => {
-                println!("Controller {} added (hotplug not fully handled yet)", which);
-            }
-            Event::ControllerDeviceRemoved { which, .. } => {
-                println!("Controller {} removed", which);
-                break;
+            // Plain joysticks are listed too, so the UI can show what isn't a game controller
+            Event::ControllerDeviceAdded { .. }
+            | Event::ControllerDeviceRemoved { .. }
+            | Event::JoyDeviceAdded { .. }
+            | Event::JoyDeviceRemoved { .. } => {
+                controllers.sync(&profile);
             }
             Event::Quit { .. } => break,
             _ => (),
@@ -206,3 +632,339 @@ pub fn start_controller(
 
     Ok(())
 }
+
+/// The opened controllers plus what's needed to open and close them.
+struct Controllers {
+    game_controller_subsystem: GameControllerSubsystem,
+    joystick_subsystem: JoystickSubsystem,
+    tx: mpsc::Sender<ControllerEvent>,
+    open: HashMap<DeviceId, OpenController>,
+}
+
+impl Controllers {
+    /// Open every attached controller the profile enables, close the ones that went
+    /// away or got disabled, and tell the UI what is attached.
+    fn sync(&mut self, profile: &Profile) {
+        let available = self.game_controller_subsystem.num_joysticks().unwrap_or(0);
+        let mut detected = Vec::new();
+        let mut wanted = HashSet::new();
+        // Open controllers keep their slots; the others get the free ones in order
+        let mut taken: HashSet<(String, usize)> = self
+            .open
+            .values()
+            .map(|c| (c.guid.clone(), c.slot))
+            .collect();
+
+        for index in 0..available {
+            let is_game_controller = self.game_controller_subsystem.is_game_controller(index);
+            let name = if is_game_controller {
+                self.game_controller_subsystem.name_for_index(index)
+            } else {
+                self.joystick_subsystem.name_for_index(index)
+            }
+            .unwrap_or_default();
+            let guid = self
+                .joystick_subsystem
+                .device_guid(index)
+                .map(|g| g.string())
+                .unwrap_or_default();
+
+            let device = instance_id_for_index(index);
+            let slot = match device.and_then(|d| self.open.get(&d)) {
+                Some(open) => open.slot,
+                None => {
+                    let slot = (0..)
+                        .find(|slot| !taken.contains(&(guid.clone(), *slot)))
+                        .unwrap_or(0);
+                    taken.insert((guid.clone(), slot));
+                    slot
+                }
+            };
+            if is_game_controller && profile.wants_controller(&name, &guid, slot) {
+                if let Some(device) = device {
+                    wanted.insert(device);
+                    if !self.open.contains_key(&device) {
+                        self.attach(index, slot, profile);
+                    }
+                }
+            }
+
+            detected.push(DetectedDevice {
+                index,
+                name,
+                guid,
+                slot,
+                is_game_controller,
+                open: device.is_some_and(|d| self.open.contains_key(&d)),
+            });
+        }
+
+        let gone: Vec<DeviceId> = self
+            .open
+            .keys()
+            .filter(|device| !wanted.contains(device))
+            .copied()
+            .collect();
+        for device in gone {
+            self.detach(device, profile);
+        }
+
+        // Newly opened controllers, or a changed profile, may start or retune an LFO
+        for dev in self.open.values_mut() {
+            dev.send_lfo(&self.tx, profile.config_for(&dev.guid, dev.slot));
+        }
+
+        let _ = self.tx.send(ControllerEvent::DeviceList(detected));
+    }
+
+    /// Advance every controller's pitch smoothing by `dt_ms`.
+    fn tick(&mut self, profile: &Profile, dt_ms: f32) {
+        let now = Instant::now();
+        for dev in self.open.values_mut() {
+            let config = profile.config_for(&dev.guid, dev.slot);
+            for event in dev.buttons.due(now) {
+                let _ = self.tx.send(event);
+            }
+            dev.poll_touchpad(&self.tx, config);
+            dev.send_pitch(&self.tx, config, dt_ms);
+            dev.update_rumble(config, now);
+        }
+    }
+
+    fn attach(&mut self, index: u32, slot: usize, profile: &Profile) {
+        let Some((controller, guid)) = open_controller(
+            &self.game_controller_subsystem,
+            &self.joystick_subsystem,
+            index,
+        ) else {
+            return;
+        };
+        let device = controller.instance_id();
+
+        // Pads without sensors, or without permission for them, just never report any
+        for sensor in [SensorType::Accelerometer, SensorType::Gyroscope] {
+            if controller.has_sensor(sensor) {
+                if let Err(e) = controller.sensor_set_enabled(sensor, true) {
+                    println!("Can't enable {:?}: {}", sensor, e);
+                }
+            }
+        }
+        announce_controller(&self.tx, profile, device, &controller, &guid, slot);
+        self.open.insert(
+            device,
+            OpenController {
+                controller,
+                guid,
+                slot,
+                buttons: ButtonMapper::new(),
+                axes: AxisMapper::new(),
+                sticks: StickMapper::new(),
+                pitch: PitchState::new(),
+                axis_values: HashMap::new(),
+                lfo_sent: None,
+                hold_sent: false,
+                layer_sent: None,
+                melodic_sent: MelodicShift::default(),
+                motion: MotionTracker::new(),
+                motion_shown: HashMap::new(),
+                touchpad: TouchpadState::new(),
+                rumble: Rumble::new(),
+                velocities: VelocityTracker::new(),
+            },
+        );
+    }
+
+    fn detach(&mut self, device: DeviceId, profile: &Profile) {
+        let Some(mut dev) = self.open.remove(&device) else {
+            return;
+        };
+        println!("Controller \"{}\" closed", dev.controller.name());
+        // Fails if the pad is already gone, which stops it just as well
+        let _ = dev.controller.set_rumble(0, 0, 0);
+        // Don't leave notes hanging or the synth bent
+        for event in dev.buttons.release_all() {
+            let _ = self.tx.send(event);
+        }
+        let _ = self.tx.send(ControllerEvent::PitchBend {
+            channel: profile.config_for(&dev.guid, dev.slot).channel,
+            value: 8192,
+        });
+        if let Some((channel, _)) = dev.lfo_sent {
+            let _ = self.tx.send(ControllerEvent::Lfo {
+                channel,
+                params: None,
+            });
+        }
+        let _ = self
+            .tx
+            .send(ControllerEvent::ControllerDisconnected { device });
+    }
+}
+
+/// sdl2 0.35 doesn't wrap `SDL_JoystickGetDeviceInstanceID`, which tells whether a
+/// joystick index refers to a controller we already have open.
+fn instance_id_for_index(index: u32) -> Option<DeviceId> {
+    let id = unsafe { sdl2::sys::SDL_JoystickGetDeviceInstanceID(index as i32) };
+    u32::try_from(id).ok()
+}
+
+fn open_controller(
+    game_controller_subsystem: &GameControllerSubsystem,
+    joystick_subsystem: &JoystickSubsystem,
+    id: u32,
+) -> Option<(GameController, String)> {
+    if !game_controller_subsystem.is_game_controller(id) {
+        println!("{} is not a game controller", id);
+        return None;
+    }
+
+    println!("Attempting to open controller {}", id);
+
+    match game_controller_subsystem.open(id) {
+        Ok(c) => {
+            println!("Success: opened \"{}\"", c.name());
+            let guid = joystick_subsystem
+                .device_guid(id)
+                .map(|g| g.string())
+                .unwrap_or_default();
+            Some((c, guid))
+        }
+        Err(e) => {
+            println!("failed: {:?}", e);
+            None
+        }
+    }
+}
+
+fn announce_controller(
+    tx: &mpsc::Sender<ControllerEvent>,
+    profile: &Profile,
+    device: DeviceId,
+    controller: &GameController,
+    guid: &str,
+    slot: usize,
+) {
+    println!("Controller mapping: {}", controller.mapping());
+    println!("Controller GUID: {}", guid);
+    if profile.calibration_for(guid, slot).is_some() {
+        println!("Using stored calibration");
+    }
+    if profile.device_profile(guid, slot).is_some() {
+        println!("Using its own device profile");
+    }
+    let config = profile.config_for(guid, slot);
+    println!("Playing on MIDI channel {}", config.channel);
+    for binding in &config.buttons {
+        println!("Configured {:?}: {}", binding.button, binding.action);
+    }
+    for binding in &config.axes {
+        println!("Configured {:?}: {}", binding.axis, binding);
+    }
+    if let Some(melodic) = &config.melodic {
+        println!(
+            "Configured melodic mode: key {} {:?}, octave {}",
+            melodic.key, melodic.scale, melodic.octave
+        );
+    }
+    for layer in &config.layers {
+        println!(
+            "Configured {} on {:?} ({:?})",
+            layer, layer.modifier, layer.mode
+        );
+    }
+    for binding in &config.sticks {
+        println!("Configured {}", binding);
+    }
+    for binding in &config.motion {
+        println!("Configured {}", binding);
+    }
+    println!(
+        "Configured pitch axis: {:?} ({:?})",
+        config.pitch_axis, config.pitch_axis_mode
+    );
+
+    let (present_buttons, present_axes) = collect_present_inputs(controller);
+    let _ = tx.send(ControllerEvent::ControllerInfo {
+        device,
+        name: controller.name(),
+        guid: guid.to_string(),
+        slot,
+        mapping: controller.mapping(),
+        buttons: present_buttons,
+        axes: present_axes,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::{ModulationTarget, Stick};
+
+    fn config(mode: PitchAxisMode, pitch_axis: Axis) -> ControllerConfig {
+        ControllerConfig {
+            pitch_axis,
+            pitch_axis_mode: mode,
+            invert_pitch: false,
+            deadzone: 0,
+            ..ControllerConfig::default()
+        }
+    }
+
+    fn axes(values: &[(Axis, i16)]) -> HashMap<Axis, i16> {
+        values.iter().copied().collect()
+    }
+
+    #[test]
+    fn bipolar_follows_the_axis_with_its_deadzone() {
+        let mut config = config(PitchAxisMode::Bipolar, Axis::LeftY);
+        assert_eq!(pitch_input(&config, &axes(&[(Axis::LeftY, 32767)])), 1.0);
+        assert_eq!(pitch_input(&config, &axes(&[(Axis::LeftY, -32768)])), -1.0);
+        config.invert_pitch = true;
+        assert_eq!(pitch_input(&config, &axes(&[(Axis::LeftY, 32767)])), -1.0);
+        config.deadzone = 4000;
+        assert_eq!(pitch_input(&config, &axes(&[(Axis::LeftY, 3000)])), 0.0);
+        assert_eq!(pitch_input(&config, &HashMap::new()), 0.0);
+    }
+
+    #[test]
+    fn unipolar_bends_one_way_from_rest() {
+        let mode = PitchAxisMode::Unipolar {
+            direction: BendDirection::Down,
+        };
+        let config = config(mode, Axis::TriggerRight);
+        assert_eq!(pitch_input(&config, &axes(&[(Axis::TriggerRight, 0)])), 0.0);
+        assert_eq!(
+            pitch_input(&config, &axes(&[(Axis::TriggerRight, 32767)])),
+            -1.0
+        );
+    }
+
+    #[test]
+    fn triggers_pull_against_each_other() {
+        let config = config(PitchAxisMode::Triggers, Axis::LeftY);
+        let both = |left, right| {
+            pitch_input(
+                &config,
+                &axes(&[(Axis::TriggerLeft, left), (Axis::TriggerRight, right)]),
+            )
+        };
+        assert_eq!(both(0, 32767), 1.0);
+        assert_eq!(both(32767, 0), -1.0);
+        assert_eq!(both(32767, 32767), 0.0);
+        assert!(config.is_pitch_axis(Axis::TriggerLeft));
+        assert!(!config.is_pitch_axis(Axis::LeftY));
+    }
+
+    #[test]
+    fn a_stick_bend_replaces_the_pitch_axis() {
+        let mut config = config(PitchAxisMode::Bipolar, Axis::LeftY);
+        assert!(config.is_pitch_axis(Axis::LeftY));
+        config.sticks.push(StickBinding {
+            stick: Stick::Right,
+            deadzone: 0,
+            radius: None,
+            angle: Some(ModulationTarget::PitchBend),
+        });
+        assert!(!config.is_pitch_axis(Axis::LeftY));
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Shaping stage between the deadzone and the MIDI value.
+///
+/// Curves are defined on the magnitude `0.0..=1.0`; bipolar input keeps its sign,
+/// so a stick bends the same way in both directions.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseCurve {
+    #[default]
+    Linear,
+    /// Fine control near center, fast towards the edge. `amount` in `0.0..=1.0`.
+    Exponential { amount: f32 },
+    /// Fast near center, fine control towards the edge. `amount` in `0.0..=1.0`.
+    Logarithmic { amount: f32 },
+    /// Flat at both ends, steep in the middle. `amount` in `0.0..=1.0`.
+    SCurve { amount: f32 },
+    /// Piecewise-linear `[input, output]` points, sorted by input.
+    Breakpoints { points: Vec<[f32; 2]> },
+}
+
+impl fmt::Display for ResponseCurve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseCurve::Linear => write!(f, "Linear"),
+            ResponseCurve::Exponential { .. } => write!(f, "Exponential"),
+            ResponseCurve::Logarithmic { .. } => write!(f, "Logarithmic"),
+            ResponseCurve::SCurve { .. } => write!(f, "S-curve"),
+            ResponseCurve::Breakpoints { .. } => write!(f, "Breakpoints"),
+        }
+    }
+}
+
+impl ResponseCurve {
+    /// One of each kind with a sensible starting shape, for preset pickers.
+    pub fn presets() -> Vec<ResponseCurve> {
+        vec![
+            ResponseCurve::Linear,
+            ResponseCurve::Exponential { amount: 0.5 },
+            ResponseCurve::Logarithmic { amount: 0.5 },
+            ResponseCurve::SCurve { amount: 0.5 },
+            ResponseCurve::default_breakpoints(),
+        ]
+    }
+
+    pub fn default_breakpoints() -> ResponseCurve {
+        ResponseCurve::Breakpoints {
+            points: vec![[0.0, 0.0], [0.5, 0.5], [1.0, 1.0]],
+        }
+    }
+
+    /// Apply to a value in `-1.0..=1.0`, preserving its sign.
+    pub fn apply(&self, norm: f32) -> f32 {
+        let magnitude = self.shape(norm.abs().clamp(0.0, 1.0));
+        norm.signum() * magnitude.clamp(0.0, 1.0)
+    }
+
+    /// The curve itself on `0.0..=1.0`.
+    pub fn shape(&self, x: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => x,
+            ResponseCurve::Exponential { amount } => x.powf(exponent(*amount)),
+            ResponseCurve::Logarithmic { amount } => 1.0 - (1.0 - x).powf(exponent(*amount)),
+            ResponseCurve::SCurve { amount } => {
+                let e = exponent(*amount);
+                let a = x.powf(e);
+                let b = (1.0 - x).powf(e);
+                if a + b > 0.0 {
+                    a / (a + b)
+                } else {
+                    x
+                }
+            }
+            ResponseCurve::Breakpoints { points } => interpolate(points, x),
+        }
+    }
+}
+
+fn exponent(amount: f32) -> f32 {
+    // amount 0 is linear, 1 is a fourth-power curve
+    1.0 + 3.0 * amount.clamp(0.0, 1.0)
+}
+
+fn interpolate(points: &[[f32; 2]], x: f32) -> f32 {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return x,
+    };
+    if x <= first[0] {
+        return first[1];
+    }
+    for pair in points.windows(2) {
+        let ([x0, y0], [x1, y1]) = (pair[0], pair[1]);
+        if x <= x1 {
+            if x1 - x0 <= f32::EPSILON {
+                return y1;
+            }
+            return y0 + (y1 - y0) * (x - x0) / (x1 - x0);
+        }
+    }
+    last[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn every_preset_keeps_the_ends() {
+        for curve in ResponseCurve::presets() {
+            assert!(close(curve.shape(0.0), 0.0), "{} at 0", curve);
+            assert!(close(curve.shape(1.0), 1.0), "{} at 1", curve);
+        }
+    }
+
+    #[test]
+    fn shapes_bend_the_middle_their_way() {
+        let amount = 0.5;
+        assert!(close(ResponseCurve::Linear.shape(0.3), 0.3));
+        assert!(ResponseCurve::Exponential { amount }.shape(0.5) < 0.5);
+        assert!(ResponseCurve::Logarithmic { amount }.shape(0.5) > 0.5);
+        let s = ResponseCurve::SCurve { amount };
+        assert!(close(s.shape(0.5), 0.5));
+        assert!(s.shape(0.25) < 0.25);
+        assert!(s.shape(0.75) > 0.75);
+    }
+
+    #[test]
+    fn amount_zero_is_linear() {
+        for curve in [
+            ResponseCurve::Exponential { amount: 0.0 },
+            ResponseCurve::Logarithmic { amount: 0.0 },
+        ] {
+            assert!(close(curve.shape(0.3), 0.3), "{}", curve);
+        }
+    }
+
+    #[test]
+    fn breakpoints_interpolate_and_hold_past_the_ends() {
+        let curve = ResponseCurve::Breakpoints {
+            points: vec![[0.2, 0.0], [0.6, 0.8], [1.0, 1.0]],
+        };
+        assert!(close(curve.shape(0.1), 0.0));
+        assert!(close(curve.shape(0.4), 0.4));
+        assert!(close(curve.shape(0.8), 0.9));
+        let empty = ResponseCurve::Breakpoints { points: Vec::new() };
+        assert!(close(empty.shape(0.7), 0.7));
+    }
+
+    #[test]
+    fn apply_keeps_the_sign() {
+        let curve = ResponseCurve::Exponential { amount: 1.0 };
+        assert!(close(curve.apply(-0.5), -curve.shape(0.5)));
+        assert!(close(curve.apply(0.5), curve.shape(0.5)));
+        assert!(close(curve.apply(-2.0), -1.0));
+    }
+}
@@ -1,21 +1,69 @@
+use crate::lfo::LfoParams;
+use crate::melodic::MelodicShift;
+use crate::motion::MotionSource;
+use crate::profile::Profile;
+use crate::touchpad::TouchPoint;
 use sdl2::controller::{Axis, Button};
 
+/// SDL joystick instance id of an opened controller; stays the same until it's unplugged.
+pub type DeviceId = u32;
+
 #[derive(Debug, Clone)]
 pub enum ControllerEvent {
     // High-level, already-mapped musical intents (consumed by MIDI worker)
-    ButtonDown, // mapped note-on button
-    ButtonUp,   // mapped note-off button
-    PitchBend(u16),
+    NoteOn { channel: u8, note: u8, velocity: u8 },
+    NoteOff { channel: u8, note: u8 },
+    ControlChange { channel: u8, controller: u8, value: u8 },
+    ProgramChange { channel: u8, program: u8 },
+    PitchBend { channel: u8, value: u16 },
+    /// Start, retune or (`None`) stop the LFO the MIDI worker runs on `channel`.
+    Lfo { channel: u8, params: Option<LfoParams> },
 
     // Raw, device-level input for UI/learning/configuration
-    RawButton { button: Button, pressed: bool },
-    RawAxis { axis: Axis, value: i16 },
+    RawButton { device: DeviceId, button: Button, pressed: bool },
+    RawAxis { device: DeviceId, axis: Axis, value: i16 },
+    RawMotion { device: DeviceId, source: MotionSource, degrees: f32 },
+    /// Finger slots of the touchpad, sent whenever a finger lands, moves or lifts.
+    RawTouch { device: DeviceId, fingers: Vec<Option<TouchPoint>> },
+    /// A hold button froze (or let go of) the device's pitch bend.
+    BendHold { device: DeviceId, held: bool },
+    /// A modifier switched the device to another layer (index into
+    /// `ControllerConfig::layers`), or back to the base bindings.
+    Layer { device: DeviceId, layer: Option<usize> },
+    /// The D-pad moved the octave or key of melodic mode.
+    Melodic { device: DeviceId, shift: MelodicShift },
 
     // Metadata about a connected controller so UI can populate controls
     ControllerInfo {
+        device: DeviceId,
         name: String,
+        guid: String,
+        slot: usize,
         mapping: String,
         buttons: Vec<Button>,
         axes: Vec<Axis>,
     },
+    /// A controller announced by `ControllerInfo` went away.
+    ControllerDisconnected { device: DeviceId },
+    /// Everything SDL currently sees, opened or not, for picking controllers in the UI.
+    DeviceList(Vec<DetectedDevice>),
+}
+
+#[derive(Debug, Clone)]
+pub struct DetectedDevice {
+    /// SDL joystick index; only stable until devices are added or removed.
+    pub index: u32,
+    pub name: String,
+    pub guid: String,
+    /// Slot it has, or would get when opened, among controllers with the same GUID.
+    pub slot: usize,
+    pub is_game_controller: bool,
+    pub open: bool,
+}
+
+/// Requests from the UI to the controller thread.
+#[derive(Debug, Clone)]
+pub enum ControllerCommand {
+    /// Replace the active profile, e.g. after editing it in the GUI.
+    UpdateProfile(Profile),
 }
@@ -0,0 +1,242 @@
+use crate::events::ControllerEvent;
+use crate::pitch::pitch_bend_from_norm;
+use eframe::egui::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// When the computer keyboard and mouse play instead of a controller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyboardMode {
+    /// Only while no controller is open.
+    #[default]
+    Fallback,
+    Always,
+    Off,
+}
+
+/// Notes on the computer keyboard and pitch bend from the mouse, read by the UI window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyboardConfig {
+    pub mode: KeyboardMode,
+    pub channel: u8,
+    pub velocity: u8,
+    /// Note of the first key in `keys`.
+    pub base_note: u8,
+    /// Key names (as egui reads them, e.g. "A", "Semicolon" or ";"), one semitone apart.
+    pub keys: Vec<String>,
+    pub octave_down: String,
+    pub octave_up: String,
+    /// Pixels of vertical drag on the bend pad for a full bend.
+    pub drag_range_px: f32,
+    /// Scrolled pixels for a full bend. The wheel bend stays until the pad is
+    /// middle-clicked.
+    pub wheel_range_px: f32,
+}
+
+impl Default for KeyboardConfig {
+    fn default() -> Self {
+        // The white keys on the home row and the black keys above, like a piano
+        let keys = [
+            "A", "W", "S", "E", "D", "F", "T", "G", "Y", "H", "U", "J", "K", "O", "L", "P",
+        ];
+        Self {
+            mode: KeyboardMode::Fallback,
+            channel: 0,
+            velocity: 100,
+            base_note: 60, // Middle C
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+            octave_down: "Z".to_string(),
+            octave_up: "X".to_string(),
+            drag_range_px: 150.0,
+            wheel_range_px: 500.0,
+        }
+    }
+}
+
+/// Turns key presses and mouse movement into the same events a controller sends.
+#[derive(Debug, Default)]
+pub struct KeyboardSource {
+    /// Notes sounding for each held key, so a key's note off matches its note on
+    /// even after an octave shift.
+    held: HashMap<Key, (u8, u8)>,
+    octave: i8,
+    /// Bend from the wheel and from a drag in progress, in `-1.0..=1.0`.
+    wheel: f32,
+    drag: f32,
+    last_bend: Option<(u8, u16)>,
+}
+
+impl KeyboardSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn octave(&self) -> i8 {
+        self.octave
+    }
+
+    pub fn key(
+        &mut self,
+        config: &KeyboardConfig,
+        key: Key,
+        pressed: bool,
+    ) -> Vec<ControllerEvent> {
+        if !pressed {
+            return self
+                .held
+                .remove(&key)
+                .map(|(channel, note)| ControllerEvent::NoteOff { channel, note })
+                .into_iter()
+                .collect();
+        }
+        if is_key(&config.octave_down, key) {
+            self.octave = (self.octave - 1).max(-4);
+        } else if is_key(&config.octave_up, key) {
+            self.octave = (self.octave + 1).min(4);
+        }
+        let Some(offset) = config.keys.iter().position(|k| is_key(k, key)) else {
+            return Vec::new();
+        };
+        // Key repeat, the note is already on
+        if self.held.contains_key(&key) {
+            return Vec::new();
+        }
+        let note = config.base_note as i32 + offset as i32 + self.octave as i32 * 12;
+        if !(0..=127).contains(&note) {
+            return Vec::new();
+        }
+        let note = note as u8;
+        self.held.insert(key, (config.channel, note));
+        vec![ControllerEvent::NoteOn {
+            channel: config.channel,
+            note,
+            velocity: config.velocity,
+        }]
+    }
+
+    /// Vertical drag on the bend pad since the press, in pixels (up is negative, as
+    /// egui counts), or `None` once the button is let go.
+    pub fn drag(&mut self, config: &KeyboardConfig, dy: Option<f32>) -> Option<ControllerEvent> {
+        self.drag = match dy {
+            Some(dy) if config.drag_range_px > 0.0 => -dy / config.drag_range_px,
+            _ => 0.0,
+        };
+        self.send_bend(config)
+    }
+
+    pub fn scroll(&mut self, config: &KeyboardConfig, dy: f32) -> Option<ControllerEvent> {
+        if config.wheel_range_px > 0.0 {
+            self.wheel = (self.wheel + dy / config.wheel_range_px).clamp(-1.0, 1.0);
+        }
+        self.send_bend(config)
+    }
+
+    pub fn recenter(&mut self, config: &KeyboardConfig) -> Option<ControllerEvent> {
+        self.wheel = 0.0;
+        self.drag = 0.0;
+        self.send_bend(config)
+    }
+
+    /// Current bend in `-1.0..=1.0`.
+    pub fn bend(&self) -> f32 {
+        (self.wheel + self.drag).clamp(-1.0, 1.0)
+    }
+
+    /// Silence everything, e.g. when a controller takes over.
+    pub fn release_all(&mut self) -> Vec<ControllerEvent> {
+        let mut out: Vec<_> = self
+            .held
+            .drain()
+            .map(|(_, (channel, note))| ControllerEvent::NoteOff { channel, note })
+            .collect();
+        if let Some((channel, value)) = self.last_bend.take() {
+            if value != 8192 {
+                out.push(ControllerEvent::PitchBend {
+                    channel,
+                    value: 8192,
+                });
+            }
+        }
+        self.wheel = 0.0;
+        self.drag = 0.0;
+        out
+    }
+
+    fn send_bend(&mut self, config: &KeyboardConfig) -> Option<ControllerEvent> {
+        let value = pitch_bend_from_norm(self.bend());
+        let sent = (config.channel, value);
+        if self.last_bend.replace(sent) == Some(sent) {
+            return None;
+        }
+        Some(ControllerEvent::PitchBend {
+            channel: config.channel,
+            value,
+        })
+    }
+}
+
+fn is_key(name: &str, key: Key) -> bool {
+    Key::from_name(name) == Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes(events: &[ControllerEvent]) -> Vec<(bool, u8)> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                ControllerEvent::NoteOn { note, .. } => Some((true, *note)),
+                ControllerEvent::NoteOff { note, .. } => Some((false, *note)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keys_play_semitones_from_the_base_note() {
+        let config = KeyboardConfig::default();
+        let mut keyboard = KeyboardSource::new();
+        assert_eq!(notes(&keyboard.key(&config, Key::A, true)), [(true, 60)]);
+        assert_eq!(notes(&keyboard.key(&config, Key::E, true)), [(true, 63)]);
+        assert_eq!(notes(&keyboard.key(&config, Key::A, false)), [(false, 60)]);
+        assert!(keyboard.key(&config, Key::Q, true).is_empty());
+    }
+
+    #[test]
+    fn key_repeat_does_not_restart_the_note() {
+        let config = KeyboardConfig::default();
+        let mut keyboard = KeyboardSource::new();
+        assert_eq!(keyboard.key(&config, Key::S, true).len(), 1);
+        assert!(keyboard.key(&config, Key::S, true).is_empty());
+        assert_eq!(notes(&keyboard.key(&config, Key::S, false)), [(false, 62)]);
+        assert!(keyboard.key(&config, Key::S, false).is_empty());
+    }
+
+    #[test]
+    fn an_octave_shift_keeps_the_held_note_off() {
+        let config = KeyboardConfig::default();
+        let mut keyboard = KeyboardSource::new();
+        keyboard.key(&config, Key::A, true);
+        assert!(keyboard.key(&config, Key::X, true).is_empty());
+        assert_eq!(keyboard.octave(), 1);
+        assert_eq!(notes(&keyboard.key(&config, Key::A, false)), [(false, 60)]);
+        assert_eq!(notes(&keyboard.key(&config, Key::A, true)), [(true, 72)]);
+    }
+
+    #[test]
+    fn notes_past_the_midi_range_are_skipped() {
+        let config = KeyboardConfig {
+            base_note: 120,
+            ..KeyboardConfig::default()
+        };
+        let mut keyboard = KeyboardSource::new();
+        // P is the 16th key, 135 is out of range
+        assert!(keyboard.key(&config, Key::P, true).is_empty());
+        assert!(keyboard.key(&config, Key::P, false).is_empty());
+        assert_eq!(notes(&keyboard.key(&config, Key::A, true)), [(true, 120)]);
+    }
+}
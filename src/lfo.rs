@@ -0,0 +1,501 @@
+use crate::controller::normalize_axis;
+use crate::events::ControllerEvent;
+use sdl2::controller::Axis;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::time::{Duration, Instant};
+
+/// MIDI clock messages per quarter note.
+const CLOCK_PPQN: f32 = 24.0;
+/// Without a clock message for this long, a synced LFO falls back to its own rate.
+const CLOCK_TIMEOUT_SECS: f32 = 0.5;
+/// How often a running LFO sends a new value. The MIDI worker loops much faster,
+/// which would only flood the port.
+const OUTPUT_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LfoShape {
+    #[default]
+    Sine,
+    Triangle,
+    Square,
+    /// A new random level every cycle.
+    SampleAndHold,
+}
+
+impl LfoShape {
+    pub fn all() -> [LfoShape; 4] {
+        [
+            LfoShape::Sine,
+            LfoShape::Triangle,
+            LfoShape::Square,
+            LfoShape::SampleAndHold,
+        ]
+    }
+}
+
+/// What the LFO is added to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LfoTarget {
+    #[default]
+    PitchBend,
+    ControlChange {
+        controller: u8,
+    },
+}
+
+/// Vibrato (or any periodic modulation) on the controller's channel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LfoConfig {
+    pub shape: LfoShape,
+    pub target: LfoTarget,
+    /// Largest swing either way: semitones for pitch bend, steps for a CC.
+    pub depth: f32,
+    /// Scales the depth from 0 (axis at rest) to 1 (full throw). Always full depth if omitted.
+    #[serde(with = "crate::profile::optional_axis_name")]
+    pub depth_axis: Option<Axis>,
+    pub rate_hz: f32,
+    /// Rate at full throw of `rate_axis`; the axis at rest gives `rate_hz`.
+    pub max_rate_hz: f32,
+    #[serde(with = "crate::profile::optional_axis_name")]
+    pub rate_axis: Option<Axis>,
+    /// Follow incoming MIDI clock with one cycle every this many beats, instead of the
+    /// rate settings. Falls back to them while no clock arrives.
+    pub sync_beats: Option<f32>,
+}
+
+impl Default for LfoConfig {
+    fn default() -> Self {
+        Self {
+            shape: LfoShape::Sine,
+            target: LfoTarget::PitchBend,
+            depth: 0.5,
+            depth_axis: None,
+            rate_hz: 5.0,
+            max_rate_hz: 8.0,
+            rate_axis: None,
+            sync_beats: None,
+        }
+    }
+}
+
+impl LfoConfig {
+    /// Resolve the axes into what the MIDI worker runs. `axes` holds the latest
+    /// (calibrated) value of each axis; `bend_range` is the synth's, in semitones.
+    pub fn params(&self, axes: &HashMap<Axis, i16>, bend_range: f32) -> LfoParams {
+        // Sticks swing both ways, triggers only one; either way the distance from rest counts
+        let amount = |axis: Option<Axis>, rest: f32| match axis {
+            Some(axis) => normalize_axis(axes.get(&axis).copied().unwrap_or(0), false).abs(),
+            None => rest,
+        };
+        let full_scale = match self.target {
+            LfoTarget::PitchBend if bend_range > 0.0 => bend_range,
+            LfoTarget::PitchBend => 1.0,
+            LfoTarget::ControlChange { .. } => 127.0,
+        };
+        let rate = amount(self.rate_axis, 0.0);
+        LfoParams {
+            shape: self.shape,
+            target: self.target,
+            depth: (self.depth / full_scale * amount(self.depth_axis, 1.0)).clamp(0.0, 1.0),
+            rate_hz: self.rate_hz + (self.max_rate_hz - self.rate_hz) * rate,
+            sync_beats: self.sync_beats,
+        }
+    }
+}
+
+/// A running LFO as sent to the MIDI worker.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LfoParams {
+    pub shape: LfoShape,
+    pub target: LfoTarget,
+    /// Swing either way as a fraction of the target's full range.
+    pub depth: f32,
+    pub rate_hz: f32,
+    pub sync_beats: Option<f32>,
+}
+
+/// Tempo from incoming MIDI clock.
+#[derive(Debug, Default)]
+struct MidiClock {
+    last_tick: Option<Instant>,
+    /// Smoothed time between clock messages, in seconds.
+    tick_secs: Option<f32>,
+}
+
+impl MidiClock {
+    fn tick(&mut self, now: Instant) {
+        if let Some(last) = self.last_tick {
+            let interval = now.duration_since(last).as_secs_f32();
+            if interval < CLOCK_TIMEOUT_SECS {
+                // Clock messages jitter, average them out a bit
+                self.tick_secs = Some(match self.tick_secs {
+                    Some(avg) => avg + (interval - avg) * 0.1,
+                    None => interval,
+                });
+            }
+        }
+        self.last_tick = Some(now);
+    }
+
+    /// Quarter notes per second, while the clock is running.
+    fn beats_per_sec(&self, now: Instant) -> Option<f32> {
+        let last = self.last_tick?;
+        if now.duration_since(last).as_secs_f32() > CLOCK_TIMEOUT_SECS {
+            return None;
+        }
+        let tick = self.tick_secs?;
+        (tick > 0.0).then(|| 1.0 / (tick * CLOCK_PPQN))
+    }
+}
+
+#[derive(Debug)]
+struct Lfo {
+    params: LfoParams,
+    /// Position in the cycle, `0.0..1.0`.
+    phase: f32,
+    held: f32,
+    rng: u32,
+    /// Last CC value written for a CC target.
+    last_cc: Option<u8>,
+}
+
+impl Lfo {
+    fn new(params: LfoParams, seed: u32) -> Self {
+        Self {
+            params,
+            phase: 0.0,
+            held: 0.0,
+            rng: seed | 1,
+            last_cc: None,
+        }
+    }
+
+    fn advance(&mut self, rate_hz: f32, dt_secs: f32) {
+        self.phase += rate_hz.max(0.0) * dt_secs;
+        if self.phase >= 1.0 {
+            self.phase = self.phase.fract();
+            // xorshift32, plenty for picking the next sample-and-hold level
+            self.rng ^= self.rng << 13;
+            self.rng ^= self.rng >> 17;
+            self.rng ^= self.rng << 5;
+            self.held = (self.rng as f32 / u32::MAX as f32) * 2.0 - 1.0;
+        }
+    }
+
+    /// Current output in `-1.0..=1.0`.
+    fn value(&self) -> f32 {
+        let p = self.phase;
+        match self.params.shape {
+            LfoShape::Sine => (p * TAU).sin(),
+            // Starts at 0 and rises first, like the sine
+            LfoShape::Triangle => 4.0 * ((p + 0.75).fract() - 0.5).abs() - 1.0,
+            LfoShape::Square => {
+                if p < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            LfoShape::SampleAndHold => self.held,
+        }
+    }
+}
+
+/// The MIDI worker's LFOs, one per channel, and the unmodulated values they ride on.
+#[derive(Debug)]
+pub(crate) struct LfoBank {
+    lfos: HashMap<u8, Lfo>,
+    bend_base: HashMap<u8, u16>,
+    cc_base: HashMap<(u8, u8), u8>,
+    clock: MidiClock,
+    last_tick: Instant,
+}
+
+impl LfoBank {
+    pub fn new() -> Self {
+        Self {
+            lfos: HashMap::new(),
+            bend_base: HashMap::new(),
+            cc_base: HashMap::new(),
+            clock: MidiClock::default(),
+            last_tick: Instant::now(),
+        }
+    }
+
+    /// Start, retune or (with `None`) stop the LFO on `channel`. Returns the
+    /// unmodulated value to send when a target stops being modulated.
+    pub fn set(&mut self, channel: u8, params: Option<LfoParams>) -> Option<ControllerEvent> {
+        if let (Some(lfo), Some(params)) = (self.lfos.get_mut(&channel), params) {
+            if lfo.params.target == params.target {
+                // Keep the phase so moving the depth or rate doesn't click
+                lfo.params = params;
+                return None;
+            }
+        }
+        let restore = self.stop(channel);
+        if let Some(params) = params {
+            let seed = 0x9E37_79B9 ^ channel as u32;
+            self.lfos.insert(channel, Lfo::new(params, seed));
+        }
+        restore
+    }
+
+    /// Feed an incoming MIDI status byte, for clock sync.
+    pub fn midi_in(&mut self, status: u8, now: Instant) {
+        match status {
+            0xF8 => self.clock.tick(now),
+            // Start: line the cycles up with the downbeat
+            0xFA => self.lfos.values_mut().for_each(|lfo| lfo.phase = 0.0),
+            _ => {}
+        }
+    }
+
+    /// Pass an outgoing event through. Values an LFO rides on are kept as its base and
+    /// go out modulated from `tick` instead, so this returns `None` for them.
+    pub fn filter(&mut self, event: ControllerEvent) -> Option<ControllerEvent> {
+        match event {
+            ControllerEvent::PitchBend { channel, value } => {
+                self.bend_base.insert(channel, value);
+                match self.lfos.get(&channel) {
+                    Some(lfo) if lfo.params.target == LfoTarget::PitchBend => None,
+                    _ => Some(event),
+                }
+            }
+            ControllerEvent::ControlChange {
+                channel,
+                controller,
+                value,
+            } => {
+                self.cc_base.insert((channel, controller), value);
+                match self.lfos.get(&channel) {
+                    Some(lfo) if lfo.params.target == LfoTarget::ControlChange { controller } => {
+                        None
+                    }
+                    _ => Some(event),
+                }
+            }
+            _ => Some(event),
+        }
+    }
+
+    /// Advance every LFO and return the modulated values to send, at most once per
+    /// `OUTPUT_INTERVAL`.
+    pub fn tick(&mut self, now: Instant) -> Vec<ControllerEvent> {
+        if now.duration_since(self.last_tick) < OUTPUT_INTERVAL {
+            return Vec::new();
+        }
+        let dt = now.duration_since(self.last_tick).as_secs_f32();
+        self.last_tick = now;
+        let beats_per_sec = self.clock.beats_per_sec(now);
+
+        let mut out = Vec::new();
+        for (channel, lfo) in &mut self.lfos {
+            let rate = match (lfo.params.sync_beats, beats_per_sec) {
+                (Some(beats), Some(bps)) if beats > 0.0 => bps / beats,
+                _ => lfo.params.rate_hz,
+            };
+            lfo.advance(rate, dt);
+            let swing = lfo.value() * lfo.params.depth;
+
+            match lfo.params.target {
+                LfoTarget::PitchBend => {
+                    let base = self.bend_base.get(channel).copied().unwrap_or(8192) as f32;
+                    let value = (base + swing * 8191.5).round().clamp(0.0, 16383.0) as u16;
+                    // Repeats are dropped by the bend thinning
+                    out.push(ControllerEvent::PitchBend {
+                        channel: *channel,
+                        value,
+                    });
+                }
+                LfoTarget::ControlChange { controller } => {
+                    let base = self
+                        .cc_base
+                        .get(&(*channel, controller))
+                        .copied()
+                        .unwrap_or(64) as f32;
+                    let value = (base + swing * 127.0).round().clamp(0.0, 127.0) as u8;
+                    if lfo.last_cc != Some(value) {
+                        lfo.last_cc = Some(value);
+                        out.push(ControllerEvent::ControlChange {
+                            channel: *channel,
+                            controller,
+                            value,
+                        });
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// Drop the LFO on `channel`, returning the unmodulated value to restore.
+    fn stop(&mut self, channel: u8) -> Option<ControllerEvent> {
+        let lfo = self.lfos.remove(&channel)?;
+        Some(match lfo.params.target {
+            LfoTarget::PitchBend => ControllerEvent::PitchBend {
+                channel,
+                value: self.bend_base.get(&channel).copied().unwrap_or(8192),
+            },
+            LfoTarget::ControlChange { controller } => ControllerEvent::ControlChange {
+                channel,
+                controller,
+                value: self
+                    .cc_base
+                    .get(&(channel, controller))
+                    .copied()
+                    .unwrap_or(64),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(target: LfoTarget, depth: f32) -> LfoParams {
+        LfoParams {
+            shape: LfoShape::Sine,
+            target,
+            depth,
+            rate_hz: 1.0,
+            sync_beats: None,
+        }
+    }
+
+    fn value_at(shape: LfoShape, phase: f32) -> f32 {
+        let mut lfo = Lfo::new(
+            LfoParams {
+                shape,
+                ..params(LfoTarget::PitchBend, 1.0)
+            },
+            1,
+        );
+        lfo.phase = phase;
+        lfo.value()
+    }
+
+    fn bend_value(event: Option<ControllerEvent>) -> Option<u16> {
+        match event {
+            Some(ControllerEvent::PitchBend { value, .. }) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn shapes_start_at_zero_and_peak_a_quarter_in() {
+        for shape in [LfoShape::Sine, LfoShape::Triangle] {
+            assert!(value_at(shape, 0.0).abs() < 1e-6);
+            assert!((value_at(shape, 0.25) - 1.0).abs() < 1e-6);
+            assert!((value_at(shape, 0.75) + 1.0).abs() < 1e-6);
+        }
+        assert_eq!(value_at(LfoShape::Triangle, 0.125), 0.5);
+        assert_eq!(value_at(LfoShape::Square, 0.1), 1.0);
+        assert_eq!(value_at(LfoShape::Square, 0.6), -1.0);
+    }
+
+    #[test]
+    fn sample_and_hold_picks_a_new_level_each_cycle() {
+        let mut lfo = Lfo::new(
+            LfoParams {
+                shape: LfoShape::SampleAndHold,
+                ..params(LfoTarget::PitchBend, 1.0)
+            },
+            7,
+        );
+        lfo.advance(1.0, 0.5);
+        assert_eq!(lfo.value(), 0.0);
+        lfo.advance(1.0, 0.6);
+        let first = lfo.value();
+        assert!((-1.0..=1.0).contains(&first) && first != 0.0);
+        lfo.advance(1.0, 0.5);
+        assert_eq!(lfo.value(), first);
+        lfo.advance(1.0, 0.5);
+        assert_ne!(lfo.value(), first);
+    }
+
+    #[test]
+    fn clock_gives_the_tempo_until_it_stops() {
+        let start = Instant::now();
+        let mut clock = MidiClock::default();
+        assert_eq!(clock.beats_per_sec(start), None);
+        // 120 BPM is 48 clock messages a second
+        let tick = Duration::from_secs_f32(1.0 / 48.0);
+        for i in 0..10 {
+            clock.tick(start + tick * i);
+        }
+        let last = start + tick * 9;
+        let bps = clock.beats_per_sec(last).expect("clock running");
+        assert!((bps - 2.0).abs() < 1e-3);
+        assert_eq!(clock.beats_per_sec(last + Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn retuning_keeps_the_phase() {
+        let mut bank = LfoBank::new();
+        let start = bank.last_tick;
+        assert!(bank
+            .set(0, Some(params(LfoTarget::PitchBend, 0.5)))
+            .is_none());
+        bank.tick(start + Duration::from_millis(100));
+        let phase = bank.lfos[&0].phase;
+        assert!(phase > 0.0);
+
+        assert!(bank
+            .set(0, Some(params(LfoTarget::PitchBend, 0.2)))
+            .is_none());
+        assert_eq!(bank.lfos[&0].phase, phase);
+        assert_eq!(bank.lfos[&0].params.depth, 0.2);
+
+        // A new target starts over
+        let cc = LfoTarget::ControlChange { controller: 1 };
+        assert_eq!(bend_value(bank.set(0, Some(params(cc, 0.2)))), Some(8192));
+        assert_eq!(bank.lfos[&0].phase, 0.0);
+    }
+
+    #[test]
+    fn stopping_restores_the_base_value() {
+        let mut bank = LfoBank::new();
+        bank.set(0, Some(params(LfoTarget::PitchBend, 0.5)));
+        let bend = ControllerEvent::PitchBend {
+            channel: 0,
+            value: 9000,
+        };
+        assert!(bank.filter(bend).is_none());
+        assert_eq!(bend_value(bank.set(0, None)), Some(9000));
+        assert!(bank.set(0, None).is_none());
+
+        bank.set(
+            1,
+            Some(params(LfoTarget::ControlChange { controller: 1 }, 0.5)),
+        );
+        let cc = ControllerEvent::ControlChange {
+            channel: 1,
+            controller: 1,
+            value: 30,
+        };
+        assert!(bank.filter(cc).is_none());
+        assert!(matches!(
+            bank.set(1, None),
+            Some(ControllerEvent::ControlChange {
+                controller: 1,
+                value: 30,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn output_waits_for_the_interval() {
+        let mut bank = LfoBank::new();
+        let start = bank.last_tick;
+        bank.set(0, Some(params(LfoTarget::PitchBend, 0.5)));
+        assert!(bank.tick(start + Duration::from_millis(1)).is_empty());
+        assert_eq!(bank.tick(start + OUTPUT_INTERVAL).len(), 1);
+    }
+}
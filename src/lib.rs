@@ -1,11 +1,41 @@
 pub mod events;
+pub mod calibration;
 pub mod controller;
+pub mod curve;
+pub mod keyboard;
+pub mod lfo;
+pub mod mapping;
+pub mod melodic;
 pub mod midi;
 pub mod midi_graph;
+pub mod motion;
+pub mod pitch;
+pub mod profile;
+pub mod rumble;
+pub mod touchpad;
 pub mod ui;
+pub mod velocity;
 
-pub use controller::{start_controller, ControllerConfig};
-pub use events::ControllerEvent;
+pub use calibration::{AxisCalibration, CalibrationRecorder, DeviceCalibration};
+pub use controller::{start_controller, BendDirection, ControllerConfig, PitchAxisMode};
+pub use curve::ResponseCurve;
+pub use events::{ControllerCommand, ControllerEvent, DeviceId};
+pub use keyboard::{KeyboardConfig, KeyboardMode, KeyboardSource};
+pub use lfo::{LfoConfig, LfoParams, LfoShape, LfoTarget};
+pub use mapping::{
+    AxisBinding, AxisMapper, AxisPolarity, ButtonAction, ButtonBinding, ButtonMapper, CcMode,
+    InternalAction, Layer, LayerMode, ModulationTarget, Stick, StickBinding, StickMapper,
+};
+pub use melodic::{MelodicConfig, MelodicShift};
 pub use midi::{spawn_input_logger, start_midi_worker};
 pub use midi_graph::{MidiEndpoint, MidiEndpointId, MidiGraph, MidiGraphError};
+pub use motion::{MotionBinding, MotionSource, MotionTracker};
+pub use pitch::{PitchQuantize, PitchRelease, PitchSmoothing, Scale};
+pub use profile::{
+    default_profile_path, load_profile, load_profile_or_default, save_enabled_controllers,
+    save_profile, DeviceProfile, EnabledController, Profile, ProfileError,
+};
+pub use rumble::{RumbleConfig, RumblePulse};
+pub use touchpad::{TouchAxis, TouchMode, TouchpadBinding};
 pub use ui::ControllerApp;
+pub use velocity::{VelocityRange, VelocitySource};
@@ -1,14 +1,55 @@
 extern crate portmidi as pm;
 
-use pitch_controller::controller::ControllerConfig;
-use pitch_controller::{start_controller, start_midi_worker, ControllerApp, MidiGraph};
+use pitch_controller::{
+    default_profile_path, load_profile_or_default, start_controller, start_midi_worker,
+    ControllerApp, MidiGraph,
+};
+use std::env;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU32;
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::thread;
 
-static CHANNEL: u8 = 0;
+/// Returns the value of `--profile <path>` / `--profile=<path>`, if given.
+fn profile_arg() -> Result<Option<PathBuf>, String> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            return args
+                .next()
+                .map(|p| Some(PathBuf::from(p)))
+                .ok_or_else(|| "--profile requires a path".to_string());
+        }
+        if let Some(p) = arg.strip_prefix("--profile=") {
+            return Ok(Some(PathBuf::from(p)));
+        }
+    }
+    Ok(None)
+}
 
 fn main() -> Result<(), eframe::Error> {
+    let profile_path = match profile_arg() {
+        Ok(path) => path.or_else(default_profile_path),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+    let profile = match &profile_path {
+        Some(path) => load_profile_or_default(path).unwrap_or_else(|e| {
+            eprintln!("Failed to load profile: {}", e);
+            std::process::exit(1);
+        }),
+        None => {
+            eprintln!("No config directory found, using the default profile");
+            Default::default()
+        }
+    };
+    if let Some(path) = &profile_path {
+        println!("Profile: {}", path.display());
+    }
+
     // initialize the PortMidi context.
     let context = pm::PortMidi::new().unwrap();
     let context = Arc::new(context);
@@ -23,19 +64,21 @@ fn main() -> Result<(), eframe::Error> {
 
     // MIDI output thread
     let (midi_tx, midi_rx) = mpsc::channel();
+    let max_bend_rate = Arc::new(AtomicU32::new(profile.max_bend_rate));
     let _midi_handle = start_midi_worker(
         Arc::clone(&context),
         Some(v_in.id()),
         v_out.id(),
         midi_rx,
-        CHANNEL,
+        Arc::clone(&max_bend_rate),
     );
 
     // Controller thread (SDL2 loop). It only sends events to the GUI thread; the GUI forwards them to MIDI.
     let (controller_tx, controller_rx) = mpsc::channel();
-    let controller_config = ControllerConfig::default();
+    let (command_tx, command_rx) = mpsc::channel();
+    let thread_profile = profile.clone();
     thread::spawn(move || {
-        if let Err(e) = start_controller(controller_tx, controller_config) {
+        if let Err(e) = start_controller(controller_tx, command_rx, thread_profile) {
             eprintln!("Controller thread error: {}", e);
         }
     });
@@ -46,7 +89,11 @@ fn main() -> Result<(), eframe::Error> {
         Box::new(ControllerApp::new(
             controller_rx,
             midi_tx.clone(),
+            command_tx.clone(),
+            Arc::clone(&max_bend_rate),
             Arc::clone(&midi_graph),
+            profile.clone(),
+            profile_path.clone(),
         ))
     };
     eframe::run_native("Pitch Controller Monitor", native_options, Box::new(app))
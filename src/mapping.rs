@@ -0,0 +1,1096 @@
+use crate::controller::{apply_deadzone, normalize_axis, normalize_unipolar, ControllerConfig};
+use crate::curve::ResponseCurve;
+use crate::events::ControllerEvent;
+use crate::melodic::MelodicShift;
+use crate::rumble::RumblePulse;
+use crate::velocity::{VelocitySource, VelocityTracker};
+use sdl2::controller::{Axis, Button};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Furthest the D-pad moves the octave either way in melodic mode.
+const MAX_OCTAVE_SHIFT: i8 = 4;
+
+/// What a bound button does when pressed (and, where it matters, released).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ButtonAction {
+    /// Note on while held, note off on release.
+    Note {
+        note: u8,
+        #[serde(default = "default_velocity")]
+        velocity: u8,
+        #[serde(default)]
+        channel: Option<u8>,
+    },
+    /// Several notes at once, or one after another `strum_ms` apart. Plays `notes` if
+    /// given, else `root` and the notes `intervals` semitones above it.
+    Chord {
+        #[serde(default)]
+        notes: Vec<u8>,
+        #[serde(default = "default_root")]
+        root: u8,
+        #[serde(default)]
+        intervals: Vec<u8>,
+        #[serde(default = "default_velocity")]
+        velocity: u8,
+        #[serde(default)]
+        channel: Option<u8>,
+        #[serde(default)]
+        strum_ms: u32,
+    },
+    /// Sends `on`/`off` to a CC, either while held or flipping on each press.
+    ControlChange {
+        controller: u8,
+        #[serde(default = "default_cc_on")]
+        on: u8,
+        #[serde(default)]
+        off: u8,
+        #[serde(default)]
+        mode: CcMode,
+        #[serde(default)]
+        channel: Option<u8>,
+    },
+    ProgramChange {
+        program: u8,
+        #[serde(default)]
+        channel: Option<u8>,
+    },
+    Internal {
+        action: InternalAction,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CcMode {
+    #[default]
+    Momentary,
+    Toggle,
+}
+
+/// Actions handled by the app itself rather than mapped to a single MIDI message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InternalAction {
+    /// Sends All Notes Off (CC 123) on the profile channel.
+    AllNotesOff,
+    /// Snaps pitch bend back to center.
+    ResetPitchBend,
+    /// Takes the current orientation as the rest position of the motion sources.
+    RecenterMotion,
+    /// Freezes the pitch bend while held, so the stick can be let go.
+    HoldBend,
+    /// Like `HoldBend`, but each press flips the hold on or off.
+    LatchBend,
+}
+
+fn default_velocity() -> u8 {
+    100
+}
+
+fn default_root() -> u8 {
+    60 // Middle C
+}
+
+/// The notes a chord binding plays, in strum order. Notes above 127 are dropped.
+fn chord_notes(notes: &[u8], root: u8, intervals: &[u8]) -> Vec<u8> {
+    if !notes.is_empty() {
+        return notes.iter().copied().filter(|n| *n <= 127).collect();
+    }
+    let mut out = vec![root];
+    for interval in intervals {
+        let note = root.saturating_add(*interval);
+        if note <= 127 && !out.contains(&note) {
+            out.push(note);
+        }
+    }
+    out
+}
+
+fn default_cc_on() -> u8 {
+    127
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ButtonBinding {
+    #[serde(with = "crate::profile::button_name")]
+    pub button: Button,
+    #[serde(flatten)]
+    pub action: ButtonAction,
+    /// Buzz the pad when the button is pressed.
+    #[serde(default)]
+    pub rumble: Option<RumblePulse>,
+    /// Takes the velocity of notes and chords from an axis or stick speed instead.
+    #[serde(default)]
+    pub velocity_source: Option<VelocitySource>,
+}
+
+/// Routes an axis to a Control Change, scaled into `min..=max`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    #[serde(with = "crate::profile::axis_name")]
+    pub axis: Axis,
+    pub controller: u8,
+    #[serde(default)]
+    pub channel: Option<u8>,
+    #[serde(default)]
+    pub min: u8,
+    #[serde(default = "default_cc_on")]
+    pub max: u8,
+    #[serde(default)]
+    pub invert: bool,
+    #[serde(default)]
+    pub deadzone: i16,
+    #[serde(default)]
+    pub curve: ResponseCurve,
+    /// Where the axis rests. Defaults to unipolar for the triggers and bipolar for
+    /// the sticks.
+    #[serde(default)]
+    pub polarity: Option<AxisPolarity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AxisPolarity {
+    /// Rests in the center, which sends the middle of `min..=max`.
+    Bipolar,
+    /// Rests at one end, like a trigger, which sends `min`.
+    Unipolar,
+}
+
+impl AxisBinding {
+    pub fn polarity(&self) -> AxisPolarity {
+        self.polarity.unwrap_or(match self.axis {
+            Axis::TriggerLeft | Axis::TriggerRight => AxisPolarity::Unipolar,
+            _ => AxisPolarity::Bipolar,
+        })
+    }
+}
+
+/// A bank of button and axis bindings that takes over while its modifier is active.
+/// Buttons and axes the layer doesn't bind keep their base bindings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    #[serde(default)]
+    pub name: String,
+    /// Switches the layer instead of playing.
+    #[serde(with = "crate::profile::button_name")]
+    pub modifier: Button,
+    #[serde(default)]
+    pub mode: LayerMode,
+    #[serde(default)]
+    pub buttons: Vec<ButtonBinding>,
+    #[serde(default)]
+    pub axes: Vec<AxisBinding>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerMode {
+    /// Active while the modifier is held.
+    #[default]
+    Momentary,
+    /// Each press of the modifier turns the layer on or off.
+    Toggle,
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "{:?} layer", self.modifier)
+        } else {
+            write!(f, "{}", self.name)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl Stick {
+    /// The X and Y axes of the stick.
+    pub fn axes(&self) -> (Axis, Axis) {
+        match self {
+            Stick::Left => (Axis::LeftX, Axis::LeftY),
+            Stick::Right => (Axis::RightX, Axis::RightY),
+        }
+    }
+
+    pub fn has(&self, axis: Axis) -> bool {
+        let (x, y) = self.axes();
+        axis == x || axis == y
+    }
+}
+
+/// Where a stick's radius or angle, or a motion sensor, goes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ModulationTarget {
+    /// Scaled into `min..=max`. A stick's radius spans center to edge and its angle a
+    /// full turn clockwise from straight up; a motion source spans full tilt one way
+    /// to full tilt the other.
+    ControlChange {
+        controller: u8,
+        #[serde(default)]
+        channel: Option<u8>,
+        #[serde(default)]
+        min: u8,
+        #[serde(default = "default_cc_on")]
+        max: u8,
+        #[serde(default)]
+        curve: ResponseCurve,
+    },
+    /// Replaces `pitch_axis` as the bend input. A stick's radius bends up whichever
+    /// way it is pushed; its angle bends down to the left and up to the right of
+    /// straight up, and returns to center when the stick rests.
+    PitchBend,
+}
+
+impl fmt::Display for ModulationTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModulationTarget::ControlChange {
+                controller,
+                min,
+                max,
+                ..
+            } => write!(f, "CC {} ({}..{})", controller, min, max),
+            ModulationTarget::PitchBend => write!(f, "pitch bend"),
+        }
+    }
+}
+
+/// Treats a stick's X/Y pair as one 2D control, read as angle and radius.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StickBinding {
+    pub stick: Stick,
+    /// Radial deadzone in raw units, applied to the distance from center.
+    #[serde(default)]
+    pub deadzone: i16,
+    #[serde(default)]
+    pub radius: Option<ModulationTarget>,
+    #[serde(default)]
+    pub angle: Option<ModulationTarget>,
+}
+
+impl StickBinding {
+    pub fn drives_pitch(&self) -> bool {
+        self.radius == Some(ModulationTarget::PitchBend)
+            || self.angle == Some(ModulationTarget::PitchBend)
+    }
+}
+
+impl fmt::Display for StickBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} stick", self.stick)?;
+        if let Some(target) = &self.radius {
+            write!(f, ", radius → {}", target)?;
+        }
+        if let Some(target) = &self.angle {
+            write!(f, ", angle → {}", target)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AxisBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CC {} ({}..{})", self.controller, self.min, self.max)?;
+        if self.invert {
+            write!(f, " inverted")?;
+        }
+        if self.curve != ResponseCurve::Linear {
+            write!(f, " {}", self.curve)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ButtonAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ButtonAction::Note { note, velocity, .. } => {
+                write!(f, "note {} vel {}", note, velocity)
+            }
+            ButtonAction::Chord {
+                notes,
+                root,
+                intervals,
+                strum_ms,
+                ..
+            } => {
+                write!(f, "chord {:?}", chord_notes(notes, *root, intervals))?;
+                if *strum_ms > 0 {
+                    write!(f, " strum {} ms", strum_ms)?;
+                }
+                Ok(())
+            }
+            ButtonAction::ControlChange {
+                controller, mode, ..
+            } => write!(f, "CC {} ({:?})", controller, mode),
+            ButtonAction::ProgramChange { program, .. } => write!(f, "program {}", program),
+            ButtonAction::Internal { action } => write!(f, "{:?}", action),
+        }
+    }
+}
+
+/// Turns button presses into MIDI events according to `ControllerConfig::buttons`.
+///
+/// The events to send on release are decided at press time, so a note always gets
+/// its matching note off even if the bindings change while the button is held.
+#[derive(Debug, Default)]
+pub struct ButtonMapper {
+    held: HashMap<Button, Vec<ControllerEvent>>,
+    /// Buttons in `held`, oldest press first.
+    order: Vec<Button>,
+    cc_toggles: HashMap<(u8, u8), bool>,
+    bend_reset_requested: bool,
+    /// Buttons bound to `HoldBend` that are down.
+    bend_holds: Vec<Button>,
+    bend_latched: bool,
+    recenter_requested: bool,
+    /// Strongest rumble pulse of the presses since the last `take_pulse`.
+    pulse: Option<RumblePulse>,
+    /// Momentary layer modifiers that are down with their layer, oldest first.
+    layers_held: Vec<(Button, usize)>,
+    layer_toggled: Option<usize>,
+    /// Strummed chord notes still to come, with their button and when they're due.
+    strums: Vec<(Button, Instant, ControllerEvent)>,
+    melodic: MelodicShift,
+}
+
+impl ButtonMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `velocities` supplies the axis values and stick speed for velocity sources.
+    pub fn press(
+        &mut self,
+        config: &ControllerConfig,
+        button: Button,
+        velocities: &VelocityTracker,
+    ) -> Vec<ControllerEvent> {
+        let now = Instant::now();
+        let mut out = Vec::new();
+        let mut release = Vec::new();
+
+        if let Some(layer) = config.layers.iter().position(|l| l.modifier == button) {
+            match config.layers[layer].mode {
+                LayerMode::Momentary => {
+                    self.layers_held.retain(|(b, _)| *b != button);
+                    self.layers_held.push((button, layer));
+                }
+                LayerMode::Toggle => {
+                    self.layer_toggled = (self.layer_toggled != Some(layer)).then_some(layer);
+                }
+            }
+            return out;
+        }
+
+        // Melodic mode's buttons play instead of their bindings
+        let bindings = if self.melodic_press(config, button, velocities, &mut out, &mut release) {
+            Vec::new()
+        } else {
+            config.button_bindings(self.active_layer(), button)
+        };
+        for binding in bindings {
+            if let Some(pulse) = binding.rumble {
+                if !self.pulse.is_some_and(|p| p.strength >= pulse.strength) {
+                    self.pulse = Some(pulse);
+                }
+            }
+            let velocity_at_press = |fixed: u8| match &binding.velocity_source {
+                Some(source) => velocities.velocity(source, now),
+                None => fixed,
+            };
+            match binding.action {
+                ButtonAction::Note {
+                    note,
+                    velocity,
+                    channel,
+                } => {
+                    let channel = channel.unwrap_or(config.channel);
+                    out.push(ControllerEvent::NoteOn {
+                        channel,
+                        note,
+                        velocity: velocity_at_press(velocity),
+                    });
+                    release.push(ControllerEvent::NoteOff { channel, note });
+                }
+                ButtonAction::Chord {
+                    ref notes,
+                    root,
+                    ref intervals,
+                    velocity,
+                    channel,
+                    strum_ms,
+                } => {
+                    let channel = channel.unwrap_or(config.channel);
+                    let velocity = velocity_at_press(velocity);
+                    for (i, note) in chord_notes(notes, root, intervals).into_iter().enumerate() {
+                        let on = ControllerEvent::NoteOn {
+                            channel,
+                            note,
+                            velocity,
+                        };
+                        if i == 0 || strum_ms == 0 {
+                            out.push(on);
+                        } else {
+                            let due = now + Duration::from_millis(strum_ms as u64 * i as u64);
+                            self.strums.push((button, due, on));
+                        }
+                        release.push(ControllerEvent::NoteOff { channel, note });
+                    }
+                }
+                ButtonAction::ControlChange {
+                    controller,
+                    on,
+                    off,
+                    mode,
+                    channel,
+                } => {
+                    let channel = channel.unwrap_or(config.channel);
+                    match mode {
+                        CcMode::Momentary => {
+                            out.push(ControllerEvent::ControlChange {
+                                channel,
+                                controller,
+                                value: on,
+                            });
+                            release.push(ControllerEvent::ControlChange {
+                                channel,
+                                controller,
+                                value: off,
+                            });
+                        }
+                        CcMode::Toggle => {
+                            let state = self.cc_toggles.entry((channel, controller)).or_default();
+                            *state = !*state;
+                            out.push(ControllerEvent::ControlChange {
+                                channel,
+                                controller,
+                                value: if *state { on } else { off },
+                            });
+                        }
+                    }
+                }
+                ButtonAction::ProgramChange { program, channel } => {
+                    out.push(ControllerEvent::ProgramChange {
+                        channel: channel.unwrap_or(config.channel),
+                        program,
+                    });
+                }
+                ButtonAction::Internal { action } => match action {
+                    InternalAction::AllNotesOff => {
+                        out.push(ControllerEvent::ControlChange {
+                            channel: config.channel,
+                            controller: 123,
+                            value: 0,
+                        });
+                    }
+                    InternalAction::ResetPitchBend => self.bend_reset_requested = true,
+                    InternalAction::HoldBend => {
+                        if !self.bend_holds.contains(&button) {
+                            self.bend_holds.push(button);
+                        }
+                    }
+                    InternalAction::LatchBend => self.bend_latched = !self.bend_latched,
+                    InternalAction::RecenterMotion => self.recenter_requested = true,
+                },
+            }
+        }
+
+        if !release.is_empty() {
+            // A repeated press without a release (shouldn't happen) still closes the old notes.
+            out.splice(0..0, self.held.remove(&button).unwrap_or_default());
+            self.held.insert(button, release);
+            self.order.retain(|b| *b != button);
+            self.order.push(button);
+        }
+        out
+    }
+
+    /// Play a degree or move the octave or key if melodic mode is on and `button` is
+    /// one of its buttons. Returns whether it was.
+    fn melodic_press(
+        &mut self,
+        config: &ControllerConfig,
+        button: Button,
+        velocities: &VelocityTracker,
+        out: &mut Vec<ControllerEvent>,
+        release: &mut Vec<ControllerEvent>,
+    ) -> bool {
+        let Some(melodic) = &config.melodic else {
+            return false;
+        };
+        let shift = &mut self.melodic;
+        if button == melodic.octave_up {
+            shift.octave = (shift.octave + 1).min(MAX_OCTAVE_SHIFT);
+        } else if button == melodic.octave_down {
+            shift.octave = (shift.octave - 1).max(-MAX_OCTAVE_SHIFT);
+        } else if button == melodic.transpose_up {
+            shift.transpose = (shift.transpose + 1).min(12);
+        } else if button == melodic.transpose_down {
+            shift.transpose = (shift.transpose - 1).max(-12);
+        } else if let Some(degree) = melodic.degrees.iter().position(|b| *b == button) {
+            if let Some(note) = melodic.note(degree, *shift) {
+                let channel = config.channel;
+                let velocity = match &melodic.velocity_source {
+                    Some(source) => velocities.velocity(source, Instant::now()),
+                    None => melodic.velocity,
+                };
+                out.push(ControllerEvent::NoteOn {
+                    channel,
+                    note,
+                    velocity,
+                });
+                release.push(ControllerEvent::NoteOff { channel, note });
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    pub fn release(&mut self, button: Button) -> Vec<ControllerEvent> {
+        self.layers_held.retain(|(b, _)| *b != button);
+        self.order.retain(|b| *b != button);
+        self.bend_holds.retain(|b| *b != button);
+        let mut events = self.held.remove(&button).unwrap_or_default();
+        self.drop_strums(button, &mut events);
+        events
+    }
+
+    /// Release everything still held, e.g. when the device goes away.
+    pub fn release_all(&mut self) -> Vec<ControllerEvent> {
+        self.layers_held.clear();
+        self.layer_toggled = None;
+        self.order.clear();
+        self.bend_holds.clear();
+        self.bend_latched = false;
+        let held: Vec<_> = self.held.drain().collect();
+        let mut out = Vec::new();
+        for (button, mut events) in held {
+            self.drop_strums(button, &mut events);
+            out.extend(events);
+        }
+        out
+    }
+
+    /// Cancel `button`'s voices that aren't strummed yet, and their note offs among
+    /// its release `events`, since they never started.
+    fn drop_strums(&mut self, button: Button, events: &mut Vec<ControllerEvent>) {
+        let mut pending = Vec::new();
+        self.strums.retain(|(b, _, event)| {
+            if *b != button {
+                return true;
+            }
+            if let ControllerEvent::NoteOn { channel, note, .. } = event {
+                pending.push((*channel, *note));
+            }
+            false
+        });
+        for (channel, note) in pending {
+            let off = events.iter().rposition(|e| match e {
+                ControllerEvent::NoteOff {
+                    channel: c,
+                    note: n,
+                } => (*c, *n) == (channel, note),
+                _ => false,
+            });
+            if let Some(i) = off {
+                events.remove(i);
+            }
+        }
+    }
+
+    /// Whether a pitch bend reset button was pressed since the last call.
+    pub fn take_bend_reset(&mut self) -> bool {
+        std::mem::take(&mut self.bend_reset_requested)
+    }
+
+    /// Octave and key moved from the D-pad in melodic mode.
+    pub fn melodic_shift(&self) -> MelodicShift {
+        self.melodic
+    }
+
+    /// Strummed notes that are due by `now`.
+    pub fn due(&mut self, now: Instant) -> Vec<ControllerEvent> {
+        let mut out = Vec::new();
+        self.strums.retain(|(_, due, event)| {
+            if *due > now {
+                return true;
+            }
+            out.push(event.clone());
+            false
+        });
+        out
+    }
+
+    /// Index into `ControllerConfig::layers` of the layer in use: the latest held
+    /// momentary one, else a toggled one. `None` is the base bindings.
+    pub fn active_layer(&self) -> Option<usize> {
+        self.layers_held
+            .last()
+            .map(|(_, layer)| *layer)
+            .or(self.layer_toggled)
+    }
+
+    /// Whether a hold button is down or the latch is on.
+    pub fn bend_held(&self) -> bool {
+        !self.bend_holds.is_empty() || self.bend_latched
+    }
+
+    /// Whether a recenter button was pressed since the last call.
+    pub fn take_recenter(&mut self) -> bool {
+        std::mem::take(&mut self.recenter_requested)
+    }
+
+    /// The rumble pulse asked for by the presses since the last call.
+    pub fn take_pulse(&mut self) -> Option<RumblePulse> {
+        self.pulse.take()
+    }
+
+    /// The most recently pressed note that is still held.
+    pub fn held_note(&self) -> Option<u8> {
+        self.order.iter().rev().find_map(|button| {
+            self.held[button].iter().find_map(|event| match event {
+                ControllerEvent::NoteOff { note, .. } => Some(*note),
+                _ => None,
+            })
+        })
+    }
+}
+
+/// Turns axis motion into Control Changes according to `ControllerConfig::axes`.
+///
+/// SDL reports 16-bit axis values, so most motion doesn't change the 7-bit CC value;
+/// only actual changes are emitted.
+#[derive(Debug, Default)]
+pub struct AxisMapper {
+    last_sent: HashMap<(u8, u8), u8>,
+}
+
+impl AxisMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn motion(
+        &mut self,
+        config: &ControllerConfig,
+        layer: Option<usize>,
+        axis: Axis,
+        value: i16,
+    ) -> Vec<ControllerEvent> {
+        let mut out = Vec::new();
+        for binding in config.axis_bindings(layer, axis) {
+            let channel = binding.channel.unwrap_or(config.channel);
+            let deadzone = (binding.deadzone as f32) / 32767.0;
+            let cc = match binding.polarity() {
+                AxisPolarity::Bipolar => {
+                    let norm = apply_deadzone(normalize_axis(value, binding.invert), deadzone);
+                    cc_from_norm(binding.curve.apply(norm), binding.min, binding.max)
+                }
+                AxisPolarity::Unipolar => {
+                    let t = binding
+                        .curve
+                        .shape(apply_deadzone(normalize_unipolar(value), deadzone));
+                    let t = if binding.invert { 1.0 - t } else { t };
+                    cc_from_unit(t, binding.min, binding.max)
+                }
+            };
+            if self.last_sent.insert((channel, binding.controller), cc) != Some(cc) {
+                out.push(ControllerEvent::ControlChange {
+                    channel,
+                    controller: binding.controller,
+                    value: cc,
+                });
+            }
+        }
+        out
+    }
+}
+
+pub(crate) fn cc_from_norm(norm: f32, min: u8, max: u8) -> u8 {
+    // Map [-1.0, 1.0] onto [min, max]; max < min simply runs the range backwards
+    cc_from_unit((norm.clamp(-1.0, 1.0) + 1.0) / 2.0, min, max)
+}
+
+/// Map `0.0..=1.0` onto `min..=max`.
+fn cc_from_unit(t: f32, min: u8, max: u8) -> u8 {
+    let v = min as f32 + (max as f32 - min as f32) * t.clamp(0.0, 1.0);
+    v.round().clamp(0.0, 127.0) as u8
+}
+
+/// Turns stick motion into polar Control Changes and pitch bend input according to
+/// `ControllerConfig::sticks`.
+#[derive(Debug, Default)]
+pub struct StickMapper {
+    /// Latest value of every axis, since a stick needs both of its axes.
+    values: HashMap<Axis, i16>,
+    last_sent: HashMap<(u8, u8), u8>,
+}
+
+impl StickMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the Control Changes to send and, if a stick drives the pitch, the bend
+    /// input in `-1.0..=1.0` and whether that stick is resting.
+    pub fn motion(
+        &mut self,
+        config: &ControllerConfig,
+        axis: Axis,
+        value: i16,
+    ) -> (Vec<ControllerEvent>, Option<(f32, bool)>) {
+        self.values.insert(axis, value);
+        let mut out = Vec::new();
+        let mut pitch = None;
+
+        for binding in config.sticks.iter().filter(|b| b.stick.has(axis)) {
+            let (x_axis, y_axis) = binding.stick.axes();
+            let x = normalize_axis(self.values.get(&x_axis).copied().unwrap_or(0), false);
+            // SDL's Y axis points down, make up positive
+            let y = normalize_axis(self.values.get(&y_axis).copied().unwrap_or(0), true);
+            let deadzone = (binding.deadzone as f32) / 32767.0;
+            let radius = apply_deadzone(x.hypot(y).min(1.0), deadzone);
+            let resting = radius == 0.0;
+            // Clockwise from straight up, in -PI..=PI
+            let angle = x.atan2(y);
+
+            let components = [
+                (&binding.radius, radius, radius, false),
+                // As a bend the angle follows the sideways position, so it doesn't
+                // jump where the turn wraps around at the bottom
+                (
+                    &binding.angle,
+                    angle.rem_euclid(TAU) / TAU,
+                    angle.sin(),
+                    true,
+                ),
+            ];
+            for (target, position, bend, is_angle) in components {
+                // The angle of a resting stick means nothing
+                let undefined = is_angle && resting;
+                match target {
+                    Some(ModulationTarget::ControlChange {
+                        controller,
+                        channel,
+                        min,
+                        max,
+                        curve,
+                    }) => {
+                        if undefined {
+                            continue;
+                        }
+                        let channel = channel.unwrap_or(config.channel);
+                        let norm = curve.apply(position) * 2.0 - 1.0;
+                        let cc = cc_from_norm(norm, *min, *max);
+                        if self.last_sent.insert((channel, *controller), cc) != Some(cc) {
+                            out.push(ControllerEvent::ControlChange {
+                                channel,
+                                controller: *controller,
+                                value: cc,
+                            });
+                        }
+                    }
+                    Some(ModulationTarget::PitchBend) => {
+                        pitch = Some((if undefined { 0.0 } else { bend }, resting));
+                    }
+                    None => {}
+                }
+            }
+        }
+        (out, pitch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cc_values(events: &[ControllerEvent]) -> Vec<u8> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                ControllerEvent::ControlChange { value, .. } => Some(*value),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn axis_binding(axis: Axis) -> AxisBinding {
+        AxisBinding {
+            axis,
+            controller: 1,
+            channel: None,
+            min: 0,
+            max: 127,
+            invert: false,
+            deadzone: 0,
+            curve: ResponseCurve::Linear,
+            polarity: None,
+        }
+    }
+
+    #[test]
+    fn cc_spans_min_to_max() {
+        assert_eq!(cc_from_norm(-1.0, 0, 127), 0);
+        assert_eq!(cc_from_norm(0.0, 0, 127), 64);
+        assert_eq!(cc_from_norm(1.0, 0, 127), 127);
+        assert_eq!(cc_from_norm(2.0, 10, 20), 20);
+        // Backwards ranges run backwards
+        assert_eq!(cc_from_norm(1.0, 127, 0), 0);
+        assert_eq!(cc_from_unit(0.0, 20, 100), 20);
+        assert_eq!(cc_from_unit(0.5, 20, 100), 60);
+    }
+
+    #[test]
+    fn triggers_send_min_at_rest_and_sticks_the_middle() {
+        let mut config = ControllerConfig {
+            axes: vec![
+                axis_binding(Axis::TriggerLeft),
+                AxisBinding {
+                    controller: 2,
+                    ..axis_binding(Axis::RightX)
+                },
+            ],
+            ..ControllerConfig::default()
+        };
+        let mut axes = AxisMapper::new();
+        assert_eq!(
+            cc_values(&axes.motion(&config, None, Axis::TriggerLeft, 0)),
+            [0]
+        );
+        let full = axes.motion(&config, None, Axis::TriggerLeft, 32767);
+        assert_eq!(cc_values(&full), [127]);
+        assert_eq!(
+            cc_values(&axes.motion(&config, None, Axis::RightX, 0)),
+            [64]
+        );
+
+        config.axes[0].polarity = Some(AxisPolarity::Bipolar);
+        let mut axes = AxisMapper::new();
+        assert_eq!(
+            cc_values(&axes.motion(&config, None, Axis::TriggerLeft, 0)),
+            [64]
+        );
+    }
+
+    #[test]
+    fn unipolar_invert_rests_at_max() {
+        let config = ControllerConfig {
+            axes: vec![AxisBinding {
+                invert: true,
+                ..axis_binding(Axis::TriggerRight)
+            }],
+            ..ControllerConfig::default()
+        };
+        let mut axes = AxisMapper::new();
+        assert_eq!(
+            cc_values(&axes.motion(&config, None, Axis::TriggerRight, 0)),
+            [127]
+        );
+    }
+
+    fn angle_bend(sticks: &mut StickMapper, config: &ControllerConfig, x: i16, y: i16) -> f32 {
+        sticks.motion(config, Axis::RightX, x);
+        let (_, pitch) = sticks.motion(config, Axis::RightY, y);
+        pitch.map(|(bend, _)| bend).unwrap_or(f32::NAN)
+    }
+
+    #[test]
+    fn angle_bend_does_not_jump_across_the_bottom() {
+        let config = ControllerConfig {
+            sticks: vec![StickBinding {
+                stick: Stick::Right,
+                deadzone: 0,
+                radius: None,
+                angle: Some(ModulationTarget::PitchBend),
+            }],
+            ..ControllerConfig::default()
+        };
+        let mut sticks = StickMapper::new();
+        assert!((angle_bend(&mut sticks, &config, -32767, 0) + 1.0).abs() < 1e-3);
+        assert!((angle_bend(&mut sticks, &config, 32767, 0) - 1.0).abs() < 1e-3);
+        // SDL's Y points down, so this is just either side of straight down
+        let left = angle_bend(&mut sticks, &config, -300, 32767);
+        let right = angle_bend(&mut sticks, &config, 300, 32767);
+        assert!(left < 0.0 && right > 0.0);
+        assert!((right - left).abs() < 0.05);
+    }
+
+    fn note(button: Button, note: u8) -> ButtonBinding {
+        ButtonBinding {
+            button,
+            action: ButtonAction::Note {
+                note,
+                velocity: 100,
+                channel: None,
+            },
+            rumble: None,
+            velocity_source: None,
+        }
+    }
+
+    fn notes(events: &[ControllerEvent]) -> Vec<(bool, u8)> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                ControllerEvent::NoteOn { note, .. } => Some((true, *note)),
+                ControllerEvent::NoteOff { note, .. } => Some((false, *note)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn layered_config(mode: LayerMode) -> ControllerConfig {
+        ControllerConfig {
+            buttons: vec![note(Button::A, 60), note(Button::B, 62)],
+            axes: vec![axis_binding(Axis::RightX)],
+            layers: vec![Layer {
+                name: String::new(),
+                modifier: Button::LeftShoulder,
+                mode,
+                buttons: vec![note(Button::A, 72)],
+                axes: vec![AxisBinding {
+                    controller: 2,
+                    ..axis_binding(Axis::RightX)
+                }],
+            }],
+            ..ControllerConfig::default()
+        }
+    }
+
+    #[test]
+    fn a_held_layer_swaps_only_the_bindings_it_has() {
+        let config = layered_config(LayerMode::Momentary);
+        let mut buttons = ButtonMapper::new();
+        assert!(buttons
+            .press(&config, Button::LeftShoulder, &VelocityTracker::new())
+            .is_empty());
+        assert_eq!(buttons.active_layer(), Some(0));
+        assert_eq!(
+            notes(&buttons.press(&config, Button::A, &VelocityTracker::new())),
+            [(true, 72)]
+        );
+        assert_eq!(
+            notes(&buttons.press(&config, Button::B, &VelocityTracker::new())),
+            [(true, 62)]
+        );
+
+        // Letting go of the modifier first still ends the note that was played
+        buttons.release(Button::LeftShoulder);
+        assert_eq!(buttons.active_layer(), None);
+        assert_eq!(notes(&buttons.release(Button::A)), [(false, 72)]);
+        assert_eq!(
+            notes(&buttons.press(&config, Button::A, &VelocityTracker::new())),
+            [(true, 60)]
+        );
+    }
+
+    #[test]
+    fn a_toggled_layer_stays_until_pressed_again() {
+        let config = layered_config(LayerMode::Toggle);
+        let mut buttons = ButtonMapper::new();
+        buttons.press(&config, Button::LeftShoulder, &VelocityTracker::new());
+        buttons.release(Button::LeftShoulder);
+        assert_eq!(buttons.active_layer(), Some(0));
+        buttons.press(&config, Button::LeftShoulder, &VelocityTracker::new());
+        assert_eq!(buttons.active_layer(), None);
+    }
+
+    #[test]
+    fn a_layer_axis_binding_replaces_the_base_one() {
+        let config = layered_config(LayerMode::Momentary);
+        let mut axes = AxisMapper::new();
+        let controllers = |events: Vec<ControllerEvent>| -> Vec<u8> {
+            events
+                .iter()
+                .filter_map(|e| match e {
+                    ControllerEvent::ControlChange { controller, .. } => Some(*controller),
+                    _ => None,
+                })
+                .collect()
+        };
+        assert_eq!(
+            controllers(axes.motion(&config, None, Axis::RightX, 100)),
+            [1]
+        );
+        assert_eq!(
+            controllers(axes.motion(&config, Some(0), Axis::RightX, 100)),
+            [2]
+        );
+    }
+
+    fn chord(strum_ms: u32) -> ButtonBinding {
+        ButtonBinding {
+            button: Button::X,
+            action: ButtonAction::Chord {
+                notes: Vec::new(),
+                root: 60,
+                intervals: vec![4, 7],
+                velocity: 100,
+                channel: None,
+                strum_ms,
+            },
+            rumble: None,
+            velocity_source: None,
+        }
+    }
+
+    #[test]
+    fn chords_build_on_the_root() {
+        assert_eq!(chord_notes(&[], 60, &[4, 7, 12]), [60, 64, 67, 72]);
+        // Repeats and notes past the MIDI range are dropped
+        assert_eq!(chord_notes(&[], 120, &[0, 7, 12]), [120, 127]);
+        assert_eq!(chord_notes(&[62, 130], 60, &[4]), [62]);
+    }
+
+    #[test]
+    fn a_strum_sends_the_notes_one_after_another() {
+        let config = ControllerConfig {
+            buttons: vec![chord(10)],
+            ..ControllerConfig::default()
+        };
+        let mut buttons = ButtonMapper::new();
+        assert_eq!(
+            notes(&buttons.press(&config, Button::X, &VelocityTracker::new())),
+            [(true, 60)]
+        );
+        let later = Instant::now() + Duration::from_secs(1);
+        assert_eq!(notes(&buttons.due(later)), [(true, 64), (true, 67)]);
+        assert_eq!(
+            notes(&buttons.release(Button::X)),
+            [(false, 60), (false, 64), (false, 67)]
+        );
+    }
+
+    #[test]
+    fn releasing_mid_strum_only_stops_notes_that_started() {
+        let config = ControllerConfig {
+            buttons: vec![chord(1000)],
+            ..ControllerConfig::default()
+        };
+        let mut buttons = ButtonMapper::new();
+        assert_eq!(
+            notes(&buttons.press(&config, Button::X, &VelocityTracker::new())),
+            [(true, 60)]
+        );
+        assert_eq!(notes(&buttons.release(Button::X)), [(false, 60)]);
+        assert!(buttons
+            .due(Instant::now() + Duration::from_secs(5))
+            .is_empty());
+    }
+}
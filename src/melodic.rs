@@ -0,0 +1,147 @@
+use crate::pitch::Scale;
+use crate::velocity::VelocitySource;
+use sdl2::controller::Button;
+use serde::{Deserialize, Serialize};
+
+/// Plays the controller as an instrument: each degree button is a step of the scale,
+/// and the D-pad moves the octave and key. Degree buttons take precedence over their
+/// `buttons` bindings while this is on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MelodicConfig {
+    /// 0 = C .. 11 = B
+    pub key: u8,
+    pub scale: Scale,
+    /// Octave of the first degree, with middle C in octave 4.
+    pub octave: i8,
+    pub velocity: u8,
+    /// Takes the velocity from an axis or stick speed instead of `velocity`.
+    pub velocity_source: Option<VelocitySource>,
+    /// Buttons playing the scale upwards from the root. Past the end of the scale
+    /// they carry on into the next octave.
+    #[serde(with = "crate::profile::button_names")]
+    pub degrees: Vec<Button>,
+    #[serde(with = "crate::profile::button_name")]
+    pub octave_up: Button,
+    #[serde(with = "crate::profile::button_name")]
+    pub octave_down: Button,
+    /// Moves the key up a semitone.
+    #[serde(with = "crate::profile::button_name")]
+    pub transpose_up: Button,
+    #[serde(with = "crate::profile::button_name")]
+    pub transpose_down: Button,
+}
+
+impl Default for MelodicConfig {
+    fn default() -> Self {
+        Self {
+            key: 0,
+            scale: Scale::Major,
+            octave: 4,
+            velocity: 100,
+            velocity_source: None,
+            degrees: vec![
+                Button::A,
+                Button::B,
+                Button::X,
+                Button::Y,
+                Button::LeftShoulder,
+                Button::RightShoulder,
+                Button::LeftStick,
+                Button::RightStick,
+            ],
+            octave_up: Button::DPadUp,
+            octave_down: Button::DPadDown,
+            transpose_up: Button::DPadRight,
+            transpose_down: Button::DPadLeft,
+        }
+    }
+}
+
+impl MelodicConfig {
+    /// Note of the `degree`th step (0 is the root), shifted by the D-pad. `None` if it
+    /// falls outside the MIDI range.
+    pub fn note(&self, degree: usize, shift: MelodicShift) -> Option<u8> {
+        let intervals = self.scale.intervals();
+        let root = self.root(shift);
+        let note = root
+            + 12 * (degree / intervals.len()) as i32
+            + intervals[degree % intervals.len()] as i32;
+        u8::try_from(note).ok().filter(|n| *n <= 127)
+    }
+
+    /// MIDI note of the root with the D-pad shift applied.
+    pub fn root(&self, shift: MelodicShift) -> i32 {
+        let octave = self.octave as i32 + shift.octave as i32;
+        (octave + 1) * 12 + self.key as i32 + shift.transpose as i32
+    }
+}
+
+/// Octave and key changes made from the D-pad, on top of the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MelodicShift {
+    pub octave: i8,
+    /// Semitones.
+    pub transpose: i8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degrees_climb_the_scale_from_the_root() {
+        let config = MelodicConfig::default();
+        let none = MelodicShift::default();
+        assert_eq!(config.note(0, none), Some(60));
+        assert_eq!(config.note(2, none), Some(64));
+        assert_eq!(config.note(6, none), Some(71));
+    }
+
+    #[test]
+    fn degrees_past_the_scale_wrap_into_the_next_octave() {
+        let config = MelodicConfig {
+            key: 9,
+            scale: Scale::MinorPentatonic,
+            ..MelodicConfig::default()
+        };
+        let none = MelodicShift::default();
+        assert_eq!(config.note(4, none), Some(79));
+        assert_eq!(config.note(5, none), Some(81));
+        assert_eq!(config.note(7, none), Some(86));
+    }
+
+    #[test]
+    fn the_shift_moves_octave_and_key() {
+        let config = MelodicConfig::default();
+        let shift = MelodicShift {
+            octave: -1,
+            transpose: 2,
+        };
+        assert_eq!(config.root(shift), 50);
+        assert_eq!(config.note(1, shift), Some(52));
+    }
+
+    #[test]
+    fn notes_outside_the_midi_range_are_none() {
+        let high = MelodicConfig {
+            key: 7,
+            octave: 9,
+            ..MelodicConfig::default()
+        };
+        let none = MelodicShift::default();
+        // G9 is 127, the top of the range
+        assert_eq!(high.note(0, none), Some(127));
+        assert_eq!(high.note(1, none), None);
+        let low = MelodicConfig {
+            octave: -1,
+            ..MelodicConfig::default()
+        };
+        let down = MelodicShift {
+            octave: 0,
+            transpose: -1,
+        };
+        assert_eq!(low.note(0, MelodicShift::default()), Some(0));
+        assert_eq!(low.note(0, down), None);
+    }
+}
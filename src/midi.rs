@@ -1,19 +1,19 @@
 use crate::events::ControllerEvent;
+use crate::lfo::LfoBank;
 use portmidi as pm;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc::{self, TryRecvError};
 use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
-
-const NOTE: u8 = 60; // Middle C
-const VELOCITY: u8 = 100;
+use std::time::{Duration, Instant};
 
 pub fn start_midi_worker(
     context: Arc<pm::PortMidi>,
     input_device_id: Option<pm::PortMidiDeviceId>,
     output_device_id: pm::PortMidiDeviceId,
     rx: mpsc::Receiver<ControllerEvent>,
-    channel: u8,
+    max_bend_rate: Arc<AtomicU32>,
 ) -> thread::JoinHandle<()> {
     thread::spawn(move || {
         let out_port = context
@@ -29,7 +29,7 @@ pub fn start_midi_worker(
         );
         println!("Press Ctrl-C to abort...");
 
-        handle_controller_and_passthrough(out_port, in_port.take(), rx, channel);
+        handle_controller_and_passthrough(out_port, in_port.take(), rx, max_bend_rate);
     })
 }
 
@@ -56,10 +56,12 @@ fn handle_controller_and_passthrough(
     mut out_port: pm::OutputPort,
     mut in_port: Option<pm::InputPort>,
     rx: mpsc::Receiver<ControllerEvent>,
-    channel: u8,
+    max_bend_rate: Arc<AtomicU32>,
 ) {
     const IDLE_SLEEP: Duration = Duration::from_millis(2);
 
+    let mut thinning = BendThinning::default();
+    let mut lfos = LfoBank::new();
     loop {
         let mut idle = true;
 
@@ -69,20 +71,35 @@ fn handle_controller_and_passthrough(
                     idle = false;
                     for event in events {
                         println!("MIDI In: {:?}", event.message);
+                        lfos.midi_in(event.message.status, Instant::now());
                         let _ = out_port.write_message(event.message);
                     }
                 }
             }
         }
 
+        let now = Instant::now();
+        thinning.max_rate = max_bend_rate.load(Ordering::Relaxed);
         match rx.try_recv() {
             Ok(event) => {
                 idle = false;
-                handle_controller_event(&mut out_port, event, channel);
+                let event = match event {
+                    ControllerEvent::Lfo { channel, params } => lfos.set(channel, params),
+                    event => lfos.filter(event),
+                };
+                if let Some(event) = event {
+                    send_event(&mut out_port, &mut thinning, event, now);
+                }
             }
             Err(TryRecvError::Empty) => {}
             Err(TryRecvError::Disconnected) => break,
         }
+        for event in lfos.tick(now) {
+            send_event(&mut out_port, &mut thinning, event, now);
+        }
+        for (channel, value) in thinning.due(now) {
+            handle_controller_event(&mut out_port, ControllerEvent::PitchBend { channel, value });
+        }
 
         if idle {
             thread::sleep(IDLE_SLEEP);
@@ -90,42 +107,187 @@ fn handle_controller_and_passthrough(
     }
 }
 
-fn handle_controller_event(out_port: &mut pm::OutputPort, event: ControllerEvent, channel: u8) {
-    match event {
-        ControllerEvent::ButtonDown => {
-            let note_on = pm::MidiMessage {
-                status: 0x90 + channel,
-                data1: NOTE,
-                data2: VELOCITY,
-                data3: 0,
-            };
-            println!("Note On: {:?}", note_on);
-            let _ = out_port.write_message(note_on);
+/// Write an event, with pitch bend going through the thinning.
+fn send_event(
+    out_port: &mut pm::OutputPort,
+    thinning: &mut BendThinning,
+    event: ControllerEvent,
+    now: Instant,
+) {
+    if let ControllerEvent::PitchBend { channel, value } = event {
+        if !thinning.offer(channel, value, now) {
+            return;
         }
-        ControllerEvent::ButtonUp => {
-            let note_off = pm::MidiMessage {
-                status: 0x80 + channel,
-                data1: NOTE,
-                data2: VELOCITY,
-                data3: 0,
-            };
-            println!("Note Off: {:?}", note_off);
-            let _ = out_port.write_message(note_off);
+    }
+    handle_controller_event(out_port, event);
+}
+
+/// Per-channel pitch bend state for dropping repeats and capping the message rate.
+#[derive(Debug, Default)]
+struct BendChannel {
+    last_sent: Option<u16>,
+    sent_at: Option<Instant>,
+    /// Newest value held back by the rate cap, sent once the interval has passed.
+    pending: Option<u16>,
+}
+
+impl BendChannel {
+    /// Whether the rate cap still forbids sending on this channel.
+    fn waiting(&self, now: Instant, interval: Duration) -> bool {
+        self.sent_at
+            .is_some_and(|at| now.duration_since(at) < interval)
+    }
+}
+
+/// Thins out pitch bend so bursts of axis events don't flood slow (5-pin DIN) synths.
+/// The last value of a burst is always sent, just delayed.
+#[derive(Debug, Default)]
+struct BendThinning {
+    /// Messages per second per channel, 0 for no cap.
+    max_rate: u32,
+    channels: HashMap<u8, BendChannel>,
+}
+
+impl BendThinning {
+    fn interval(&self) -> Duration {
+        if self.max_rate == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(1.0 / self.max_rate as f64)
         }
-        ControllerEvent::PitchBend(value) => {
-            let pitch_bend = pm::MidiMessage {
-                status: 0xE0 + channel,
-                data1: (value & 0x7F) as u8,
-                data2: ((value >> 7) & 0x7F) as u8,
-                data3: 0,
-            };
-            println!("Pitch Bend: {:?}", pitch_bend);
-            let _ = out_port.write_message(pitch_bend);
+    }
+
+    /// Returns true if `value` should be sent now; otherwise it is dropped as a repeat
+    /// or kept for `due`.
+    fn offer(&mut self, channel: u8, value: u16, now: Instant) -> bool {
+        let interval = self.interval();
+        let state = self.channels.entry(channel).or_default();
+        if state.last_sent == Some(value) {
+            // Back where we were, whatever was pending is moot
+            state.pending = None;
+            return false;
         }
+        if state.waiting(now, interval) {
+            state.pending = Some(value);
+            return false;
+        }
+        state.last_sent = Some(value);
+        state.sent_at = Some(now);
+        state.pending = None;
+        true
+    }
+
+    /// Held-back values whose channel may send again.
+    fn due(&mut self, now: Instant) -> Vec<(u8, u16)> {
+        let interval = self.interval();
+        let mut due = Vec::new();
+        for (channel, state) in &mut self.channels {
+            if state.waiting(now, interval) {
+                continue;
+            }
+            if let Some(value) = state.pending.take() {
+                state.last_sent = Some(value);
+                state.sent_at = Some(now);
+                due.push((*channel, value));
+            }
+        }
+        due
+    }
+}
+
+fn handle_controller_event(out_port: &mut pm::OutputPort, event: ControllerEvent) {
+    let message = match event {
+        ControllerEvent::NoteOn {
+            channel,
+            note,
+            velocity,
+        } => channel_message(0x90, channel, note, velocity),
+        ControllerEvent::NoteOff { channel, note } => channel_message(0x80, channel, note, 0),
+        ControllerEvent::ControlChange {
+            channel,
+            controller,
+            value,
+        } => channel_message(0xB0, channel, controller, value),
+        ControllerEvent::ProgramChange { channel, program } => {
+            channel_message(0xC0, channel, program, 0)
+        }
+        ControllerEvent::PitchBend { channel, value } => channel_message(
+            0xE0,
+            channel,
+            (value & 0x7F) as u8,
+            ((value >> 7) & 0x7F) as u8,
+        ),
         ControllerEvent::RawButton { .. }
         | ControllerEvent::RawAxis { .. }
-        | ControllerEvent::ControllerInfo { .. } => {
+        | ControllerEvent::RawMotion { .. }
+        | ControllerEvent::RawTouch { .. }
+        | ControllerEvent::BendHold { .. }
+        | ControllerEvent::Layer { .. }
+        | ControllerEvent::Melodic { .. }
+        | ControllerEvent::ControllerInfo { .. }
+        | ControllerEvent::ControllerDisconnected { .. }
+        | ControllerEvent::DeviceList(_)
+        | ControllerEvent::Lfo { .. } => {
             // MIDI worker ignores raw/UI-only events
+            return;
         }
+    };
+    println!("MIDI Out: {:?}", message);
+    let _ = out_port.write_message(message);
+}
+
+fn channel_message(status: u8, channel: u8, data1: u8, data2: u8) -> pm::MidiMessage {
+    pm::MidiMessage {
+        status: status | (channel & 0x0F),
+        data1: data1 & 0x7F,
+        data2: data2 & 0x7F,
+        data3: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeats_are_dropped_without_a_cap() {
+        let mut thinning = BendThinning::default();
+        let now = Instant::now();
+        assert!(thinning.offer(0, 9000, now));
+        assert!(!thinning.offer(0, 9000, now));
+        assert!(thinning.offer(0, 9100, now));
+        // Channels are counted apart
+        assert!(thinning.offer(1, 9100, now));
+        assert!(thinning.due(now).is_empty());
+    }
+
+    #[test]
+    fn the_cap_holds_back_the_latest_value() {
+        let mut thinning = BendThinning {
+            max_rate: 100,
+            ..BendThinning::default()
+        };
+        let start = Instant::now();
+        assert!(thinning.offer(0, 9000, start));
+        let soon = start + Duration::from_millis(4);
+        assert!(!thinning.offer(0, 9100, soon));
+        assert!(!thinning.offer(0, 9200, soon));
+        assert!(thinning.due(soon).is_empty());
+        let later = start + Duration::from_millis(10);
+        assert_eq!(thinning.due(later), vec![(0, 9200)]);
+        assert!(thinning.due(later).is_empty());
+    }
+
+    #[test]
+    fn returning_to_the_sent_value_cancels_the_pending_one() {
+        let mut thinning = BendThinning {
+            max_rate: 100,
+            ..BendThinning::default()
+        };
+        let start = Instant::now();
+        assert!(thinning.offer(0, 9000, start));
+        assert!(!thinning.offer(0, 9100, start + Duration::from_millis(2)));
+        assert!(!thinning.offer(0, 9000, start + Duration::from_millis(4)));
+        assert!(thinning.due(start + Duration::from_millis(20)).is_empty());
     }
 }
@@ -0,0 +1,260 @@
+use crate::controller::{apply_deadzone, ControllerConfig};
+use crate::events::ControllerEvent;
+use crate::mapping::{cc_from_norm, ModulationTarget};
+use sdl2::sensor::SensorType;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Instant;
+
+/// Longest gap between gyro samples that is still integrated, in seconds. Longer gaps
+/// (the sensor was off, the app stalled) would turn one stale reading into a jump.
+const MAX_GYRO_GAP_SECS: f32 = 0.1;
+
+/// Orientation read from a pad's motion sensors, in degrees from the rest position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionSource {
+    /// Tilting the top of the pad away from or towards the player (accelerometer).
+    TiltForward,
+    /// Rolling the pad left or right (accelerometer).
+    TiltSide,
+    /// Turning the pad left or right while level (integrated gyro, drifts over time).
+    Yaw,
+}
+
+impl MotionSource {
+    pub fn all() -> [MotionSource; 3] {
+        [
+            MotionSource::TiltForward,
+            MotionSource::TiltSide,
+            MotionSource::Yaw,
+        ]
+    }
+}
+
+/// Routes a motion source to pitch bend or a CC.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MotionBinding {
+    pub source: MotionSource,
+    /// Degrees from rest for full scale; negative flips the direction.
+    #[serde(default = "default_range_deg")]
+    pub range_deg: f32,
+    #[serde(default)]
+    pub deadzone_deg: f32,
+    pub target: ModulationTarget,
+}
+
+fn default_range_deg() -> f32 {
+    45.0
+}
+
+impl fmt::Display for MotionBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} ±{}° → {}",
+            self.source,
+            self.range_deg.abs(),
+            self.target
+        )
+    }
+}
+
+/// Orientation of one controller from its sensor updates, mapped according to
+/// `ControllerConfig::motion`.
+#[derive(Debug, Default)]
+pub struct MotionTracker {
+    /// Forward and side tilt from the last accelerometer sample.
+    tilt: Option<(f32, f32)>,
+    /// Tilt taken as rest; the first sample unless recentered.
+    rest: Option<(f32, f32)>,
+    yaw: f32,
+    last_gyro: Option<Instant>,
+    last_sent: HashMap<(u8, u8), u8>,
+}
+
+impl MotionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sensor(&mut self, sensor: SensorType, data: [f32; 3], now: Instant) {
+        match sensor {
+            SensorType::Accelerometer => {
+                // SDL: +X right, +Y up, +Z towards the player; at rest this is gravity
+                let [x, y, z] = data;
+                let tilt = (z.atan2(y).to_degrees(), x.atan2(y).to_degrees());
+                self.rest.get_or_insert(tilt);
+                self.tilt = Some(tilt);
+            }
+            SensorType::Gyroscope => {
+                // Radians per second; Y is the rotation around the up axis
+                if let Some(last) = self.last_gyro {
+                    let dt = now.duration_since(last).as_secs_f32();
+                    if dt < MAX_GYRO_GAP_SECS {
+                        self.yaw = wrap_degrees(self.yaw + data[1].to_degrees() * dt);
+                    }
+                }
+                self.last_gyro = Some(now);
+            }
+            SensorType::Unknown => {}
+        }
+    }
+
+    /// Take the current orientation as rest.
+    pub fn recenter(&mut self) {
+        self.rest = self.tilt;
+        self.yaw = 0.0;
+    }
+
+    /// Degrees from rest, once the sensor behind `source` has reported.
+    pub fn angle(&self, source: MotionSource) -> Option<f32> {
+        match source {
+            MotionSource::TiltForward => Some(wrap_degrees(self.tilt?.0 - self.rest?.0)),
+            MotionSource::TiltSide => Some(wrap_degrees(self.tilt?.1 - self.rest?.1)),
+            MotionSource::Yaw => self.last_gyro.map(|_| self.yaw),
+        }
+    }
+
+    /// Returns the Control Changes to send and, if a source drives the pitch, the bend
+    /// input in `-1.0..=1.0` and whether it is inside its deadzone.
+    pub fn map(
+        &mut self,
+        config: &ControllerConfig,
+    ) -> (Vec<ControllerEvent>, Option<(f32, bool)>) {
+        let mut out = Vec::new();
+        let mut pitch = None;
+        for binding in &config.motion {
+            let Some(angle) = self.angle(binding.source) else {
+                continue;
+            };
+            if binding.range_deg == 0.0 {
+                continue;
+            }
+            let deadzone = (binding.deadzone_deg / binding.range_deg).abs();
+            let norm = apply_deadzone((angle / binding.range_deg).clamp(-1.0, 1.0), deadzone);
+            match &binding.target {
+                ModulationTarget::ControlChange {
+                    controller,
+                    channel,
+                    min,
+                    max,
+                    curve,
+                } => {
+                    let channel = channel.unwrap_or(config.channel);
+                    let cc = cc_from_norm(curve.apply(norm), *min, *max);
+                    if self.last_sent.insert((channel, *controller), cc) != Some(cc) {
+                        out.push(ControllerEvent::ControlChange {
+                            channel,
+                            controller: *controller,
+                            value: cc,
+                        });
+                    }
+                }
+                ModulationTarget::PitchBend => pitch = Some((norm, norm == 0.0)),
+            }
+        }
+        (out, pitch)
+    }
+}
+
+fn wrap_degrees(deg: f32) -> f32 {
+    (deg + 180.0).rem_euclid(360.0) - 180.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::ResponseCurve;
+    use std::time::Duration;
+
+    /// Gravity as the accelerometer sees it with the top tilted `deg` away.
+    fn tilted_forward(deg: f32) -> [f32; 3] {
+        let (sin, cos) = deg.to_radians().sin_cos();
+        [0.0, 9.81 * cos, 9.81 * sin]
+    }
+
+    fn near(a: Option<f32>, b: f32) -> bool {
+        a.is_some_and(|a| (a - b).abs() < 1e-3)
+    }
+
+    #[test]
+    fn tilt_counts_from_the_first_sample_until_recentered() {
+        let now = Instant::now();
+        let mut motion = MotionTracker::new();
+        assert_eq!(motion.angle(MotionSource::TiltForward), None);
+        motion.sensor(SensorType::Accelerometer, tilted_forward(10.0), now);
+        assert!(near(motion.angle(MotionSource::TiltForward), 0.0));
+        motion.sensor(SensorType::Accelerometer, tilted_forward(40.0), now);
+        assert!(near(motion.angle(MotionSource::TiltForward), 30.0));
+        assert!(near(motion.angle(MotionSource::TiltSide), 0.0));
+        motion.recenter();
+        assert!(near(motion.angle(MotionSource::TiltForward), 0.0));
+    }
+
+    #[test]
+    fn yaw_skips_gaps_between_gyro_samples() {
+        let start = Instant::now();
+        let mut motion = MotionTracker::new();
+        assert_eq!(motion.angle(MotionSource::Yaw), None);
+        let turning = [0.0, 1.0_f32.to_radians() * 100.0, 0.0];
+        motion.sensor(SensorType::Gyroscope, turning, start);
+        assert!(near(motion.angle(MotionSource::Yaw), 0.0));
+        // 100 degrees per second for 50 ms
+        motion.sensor(
+            SensorType::Gyroscope,
+            turning,
+            start + Duration::from_millis(50),
+        );
+        assert!(near(motion.angle(MotionSource::Yaw), 5.0));
+        // A stall of a second is not a second of turning
+        motion.sensor(
+            SensorType::Gyroscope,
+            turning,
+            start + Duration::from_secs(1),
+        );
+        assert!(near(motion.angle(MotionSource::Yaw), 5.0));
+        motion.recenter();
+        assert!(near(motion.angle(MotionSource::Yaw), 0.0));
+    }
+
+    #[test]
+    fn map_scales_past_the_deadzone() {
+        let mut config = ControllerConfig {
+            motion: vec![MotionBinding {
+                source: MotionSource::TiltForward,
+                range_deg: 45.0,
+                deadzone_deg: 9.0,
+                target: ModulationTarget::PitchBend,
+            }],
+            ..ControllerConfig::default()
+        };
+        let now = Instant::now();
+        let mut motion = MotionTracker::new();
+        assert_eq!(motion.map(&config).1, None);
+        motion.sensor(SensorType::Accelerometer, tilted_forward(0.0), now);
+        motion.sensor(SensorType::Accelerometer, tilted_forward(5.0), now);
+        assert_eq!(motion.map(&config).1, Some((0.0, true)));
+        motion.sensor(SensorType::Accelerometer, tilted_forward(27.0), now);
+        let (bend, resting) = motion.map(&config).1.expect("pitch bound");
+        assert!((bend - 0.5).abs() < 1e-3 && !resting);
+
+        // A negative range flips the direction
+        config.motion[0].range_deg = -45.0;
+        config.motion[0].target = ModulationTarget::ControlChange {
+            controller: 1,
+            channel: None,
+            min: 0,
+            max: 127,
+            curve: ResponseCurve::Linear,
+        };
+        motion.sensor(SensorType::Accelerometer, tilted_forward(60.0), now);
+        assert!(matches!(
+            motion.map(&config).0[..],
+            [ControllerEvent::ControlChange { value: 0, .. }]
+        ));
+        // Unchanged values aren't sent again
+        assert!(motion.map(&config).0.is_empty());
+    }
+}
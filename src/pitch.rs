@@ -0,0 +1,476 @@
+use crate::controller::ControllerConfig;
+use crate::curve::ResponseCurve;
+use crate::mapping::ButtonMapper;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Smoothing of the pitch bend output. Both stages are off at 0.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PitchSmoothing {
+    /// Time constant of the one-pole low-pass, in milliseconds.
+    pub time_constant_ms: f32,
+    /// Largest change of the bend value (0-16383) allowed per millisecond.
+    pub max_slew_per_ms: f32,
+}
+
+impl PitchSmoothing {
+    pub fn is_enabled(&self) -> bool {
+        self.time_constant_ms > 0.0 || self.max_slew_per_ms > 0.0
+    }
+}
+
+/// Glide back to center once the stick is let go, instead of following SDL's
+/// near-instant snap. Off at `time_ms` 0.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PitchRelease {
+    pub time_ms: f32,
+    /// How much of the way back has been covered over the release time.
+    pub curve: ResponseCurve,
+}
+
+/// Snapping of the stick to discrete pitches for a "fretted" feel.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum PitchQuantize {
+    /// Continuous bend.
+    #[default]
+    Off,
+    /// Whole semitone steps.
+    Semitones,
+    /// Only steps landing on notes of `scale` in `key` (0 = C .. 11 = B), counted from
+    /// the held note; from the key's root when no note is held.
+    Scale { key: u8, scale: Scale },
+}
+
+impl fmt::Display for PitchQuantize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PitchQuantize::Off => write!(f, "Off"),
+            PitchQuantize::Semitones => write!(f, "Semitones"),
+            PitchQuantize::Scale { .. } => write!(f, "Scale"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scale {
+    #[default]
+    Major,
+    Minor,
+    HarmonicMinor,
+    Dorian,
+    Mixolydian,
+    MajorPentatonic,
+    MinorPentatonic,
+    Blues,
+}
+
+impl Scale {
+    pub fn all() -> [Scale; 8] {
+        [
+            Scale::Major,
+            Scale::Minor,
+            Scale::HarmonicMinor,
+            Scale::Dorian,
+            Scale::Mixolydian,
+            Scale::MajorPentatonic,
+            Scale::MinorPentatonic,
+            Scale::Blues,
+        ]
+    }
+
+    /// Semitones above the root.
+    pub fn intervals(&self) -> &'static [u8] {
+        match self {
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::HarmonicMinor => &[0, 2, 3, 5, 7, 8, 11],
+            Scale::Dorian => &[0, 2, 3, 5, 7, 9, 10],
+            Scale::Mixolydian => &[0, 2, 4, 5, 7, 9, 10],
+            Scale::MajorPentatonic => &[0, 2, 4, 7, 9],
+            Scale::MinorPentatonic => &[0, 3, 5, 7, 10],
+            Scale::Blues => &[0, 3, 5, 6, 7, 10],
+        }
+    }
+
+    pub fn contains(&self, key: u8, note: i32) -> bool {
+        let degree = (note - key as i32).rem_euclid(12) as u8;
+        self.intervals().contains(&degree)
+    }
+}
+
+pub(crate) fn pitch_bend_from_norm(norm: f32) -> u16 {
+    // Map [-1.0, 1.0] to [0, 16383] with center 8192
+    let v = ((norm + 1.0) * 8191.5).round();
+    v.clamp(0.0, 16383.0) as u16
+}
+
+/// Stick position in `-1.0..=1.0` to semitones, with separate ranges for each direction.
+fn bend_semitones(config: &ControllerConfig, norm: f32) -> f32 {
+    let (up, down) = bend_reach(config);
+    if norm >= 0.0 {
+        norm * up
+    } else {
+        norm * down
+    }
+}
+
+/// Semitones reached at full throw up and down; the synth's range unless set.
+fn bend_reach(config: &ControllerConfig) -> (f32, f32) {
+    let range = config.pitch_bend_range;
+    (
+        config.pitch_bend_up.unwrap_or(range).max(0.0),
+        config.pitch_bend_down.unwrap_or(range).max(0.0),
+    )
+}
+
+/// Snap a bend in semitones to the allowed steps, staying within `-down..=up`.
+pub(crate) fn quantize_bend(
+    quantize: &PitchQuantize,
+    (up, down): (f32, f32),
+    held_note: Option<u8>,
+    semitones: f32,
+) -> f32 {
+    let (up, down) = (up.floor() as i32, down.floor() as i32);
+    match quantize {
+        PitchQuantize::Off => semitones,
+        PitchQuantize::Semitones => semitones.round().clamp(-down as f32, up as f32),
+        PitchQuantize::Scale { key, scale } => {
+            let base = held_note.map_or(*key as i32, |n| n as i32);
+            // Staying on the held note is always allowed, even if it's off the scale
+            (-down..=up)
+                .filter(|k| *k == 0 || scale.contains(*key, base + k))
+                .map(|k| k as f32)
+                .min_by(|a, b| {
+                    let (da, db) = ((a - semitones).abs(), (b - semitones).abs());
+                    da.total_cmp(&db).then(a.abs().total_cmp(&b.abs()))
+                })
+                .unwrap_or(0.0)
+        }
+    }
+}
+
+/// Semitones to a bend in `-1.0..=1.0` for a synth with the given symmetric range.
+fn norm_from_semitones(semitones: f32, range: f32) -> f32 {
+    if range <= 0.0 {
+        return 0.0;
+    }
+    (semitones / range).clamp(-1.0, 1.0)
+}
+
+/// Pitch bend output of one controller.
+///
+/// Axis events only move the input; `tick` runs on the controller loop's timer and
+/// moves the output towards it, so glides and smoothing keep converging between SDL
+/// events.
+#[derive(Debug)]
+pub(crate) struct PitchState {
+    /// Stick position after the curve, in `-1.0..=1.0`.
+    input: f32,
+    /// `input` after quantization.
+    target: f32,
+    /// Position of the glide between quantized steps.
+    stepped: f32,
+    /// Glide speed towards `target`, in normalized units per millisecond.
+    glide_speed: f32,
+    /// Whether the stick is inside its deadzone.
+    resting: bool,
+    /// Where a running release started and how long it has run, in milliseconds.
+    release: Option<(f32, f32)>,
+    /// Bend frozen by a hold button.
+    frozen: Option<f32>,
+    /// Set when a quantized bend moves to another step, until taken.
+    step_changed: bool,
+    current: f32,
+    last_sent: Option<u16>,
+}
+
+impl PitchState {
+    pub fn new() -> Self {
+        Self {
+            input: 0.0,
+            target: 0.0,
+            stepped: 0.0,
+            glide_speed: 0.0,
+            resting: true,
+            release: None,
+            frozen: None,
+            step_changed: false,
+            current: 0.0,
+            last_sent: None,
+        }
+    }
+
+    /// Set where the stick is, in `-1.0..=1.0`, and whether it is inside the deadzone.
+    pub fn set_input(&mut self, norm: f32, resting: bool) {
+        self.input = norm.clamp(-1.0, 1.0);
+        if resting && !self.resting {
+            // Let go: release from wherever the bend is now
+            self.release = Some((self.stepped, 0.0));
+        } else if !resting && self.resting {
+            self.release = None;
+        }
+        self.resting = resting;
+    }
+
+    /// Jump back to center, dropping any glide, release or smoothing in progress. A
+    /// held bend stays held, at center. The next `tick` sends the centered bend.
+    pub fn reset(&mut self) {
+        self.input = 0.0;
+        self.target = 0.0;
+        self.stepped = 0.0;
+        self.current = 0.0;
+        self.release = None;
+        if self.frozen.is_some() {
+            self.frozen = Some(0.0);
+        }
+        self.last_sent = None;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// The bend going out, in `-1.0..=1.0`.
+    pub fn output(&self) -> f32 {
+        self.current
+    }
+
+    /// Whether a quantized bend moved to another step since the last call.
+    pub fn take_step(&mut self) -> bool {
+        std::mem::take(&mut self.step_changed)
+    }
+
+    /// Advance by `dt_ms` and return the bend value if it changed since the last one.
+    /// `buttons` supplies the held note for scale quantization and the bend hold.
+    pub fn tick(
+        &mut self,
+        config: &ControllerConfig,
+        buttons: &ButtonMapper,
+        dt_ms: f32,
+    ) -> Option<u16> {
+        let held_note = buttons.held_note();
+        match (buttons.bend_held(), self.frozen) {
+            (true, None) => self.frozen = Some(self.stepped),
+            (false, Some(frozen)) => {
+                // Back to the stick with the release envelope, or at once without one
+                self.frozen = None;
+                self.release = (config.pitch_release.time_ms > 0.0).then_some((frozen, 0.0));
+            }
+            _ => {}
+        }
+        let semitones = quantize_bend(
+            &config.pitch_quantize,
+            bend_reach(config),
+            held_note,
+            bend_semitones(config, self.input),
+        );
+        let target = norm_from_semitones(semitones, config.pitch_bend_range);
+        if target != self.target {
+            self.target = target;
+            self.step_changed |= config.pitch_quantize != PitchQuantize::Off;
+            if config.pitch_glide_ms > 0.0 {
+                // Every step takes the same time, however far it goes
+                self.glide_speed = (target - self.stepped).abs() / config.pitch_glide_ms;
+            }
+        }
+        let gliding = config.pitch_quantize != PitchQuantize::Off && config.pitch_glide_ms > 0.0;
+        let release = &config.pitch_release;
+        if let Some(frozen) = self.frozen {
+            self.stepped = frozen;
+        } else if let Some((from, elapsed)) =
+            self.release.as_mut().filter(|_| release.time_ms > 0.0)
+        {
+            *elapsed += dt_ms;
+            let t = (*elapsed / release.time_ms).min(1.0);
+            self.stepped = self.target + (*from - self.target) * (1.0 - release.curve.shape(t));
+            if t >= 1.0 {
+                self.release = None;
+            }
+        } else if gliding {
+            let max_step = self.glide_speed * dt_ms;
+            self.stepped += (self.target - self.stepped).clamp(-max_step, max_step);
+        } else {
+            self.stepped = self.target;
+        }
+
+        let smoothing = &config.pitch_smoothing;
+        if !smoothing.is_enabled() {
+            self.current = self.stepped;
+        } else {
+            let mut next = self.stepped;
+            if smoothing.time_constant_ms > 0.0 {
+                let alpha = 1.0 - (-dt_ms / smoothing.time_constant_ms).exp();
+                next = self.current + (self.stepped - self.current) * alpha;
+            }
+            if smoothing.max_slew_per_ms > 0.0 {
+                // The slew limit is in bend units, the state is normalized
+                let max_step = smoothing.max_slew_per_ms * dt_ms / 8191.5;
+                next = self.current + (next - self.current).clamp(-max_step, max_step);
+            }
+            // The low-pass only approaches the target, snap once it can't be heard
+            if (self.stepped - next).abs() * 8191.5 < 0.5 {
+                next = self.stepped;
+            }
+            self.current = next;
+        }
+
+        let value = pitch_bend_from_norm(self.current);
+        if self.last_sent == Some(value) {
+            return None;
+        }
+        self.last_sent = Some(value);
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::{ButtonAction, ButtonBinding, InternalAction};
+    use crate::velocity::VelocityTracker;
+    use sdl2::controller::Button;
+
+    const C_MAJOR: PitchQuantize = PitchQuantize::Scale {
+        key: 0,
+        scale: Scale::Major,
+    };
+
+    #[test]
+    fn reach_follows_the_range_unless_set() {
+        let mut config = ControllerConfig {
+            pitch_bend_range: 2.0,
+            ..ControllerConfig::default()
+        };
+        assert_eq!(bend_reach(&config), (2.0, 2.0));
+        config.pitch_bend_up = Some(12.0);
+        config.pitch_bend_down = Some(-1.0);
+        assert_eq!(bend_reach(&config), (12.0, 0.0));
+    }
+
+    #[test]
+    fn off_passes_the_bend_through() {
+        assert_eq!(
+            quantize_bend(&PitchQuantize::Off, (2.0, 2.0), None, 1.3),
+            1.3
+        );
+    }
+
+    #[test]
+    fn semitones_round_within_the_reach() {
+        let q = PitchQuantize::Semitones;
+        assert_eq!(quantize_bend(&q, (2.0, 2.0), None, 1.4), 1.0);
+        assert_eq!(quantize_bend(&q, (2.0, 2.0), None, -1.6), -2.0);
+        // A fractional reach only allows whole steps inside it
+        assert_eq!(quantize_bend(&q, (1.5, 2.0), None, 1.6), 1.0);
+    }
+
+    #[test]
+    fn scale_steps_count_from_the_held_note() {
+        // From C, C# is off the scale so 1.4 lands on D
+        assert_eq!(quantize_bend(&C_MAJOR, (2.0, 2.0), None, 1.4), 2.0);
+        // From E, one semitone up is F
+        assert_eq!(quantize_bend(&C_MAJOR, (2.0, 2.0), Some(64), 1.2), 1.0);
+        // Equally far steps prefer the smaller bend
+        assert_eq!(quantize_bend(&C_MAJOR, (2.0, 2.0), None, 1.0), 0.0);
+    }
+
+    #[test]
+    fn an_off_scale_held_note_can_stay_put() {
+        // C# is off the scale, but stays allowed while it is held
+        assert_eq!(quantize_bend(&C_MAJOR, (2.0, 2.0), Some(61), 0.4), 0.0);
+        assert_eq!(quantize_bend(&C_MAJOR, (2.0, 2.0), Some(61), 0.8), 1.0);
+    }
+
+    #[test]
+    fn bend_values_span_the_midi_range() {
+        assert_eq!(pitch_bend_from_norm(-1.0), 0);
+        assert_eq!(pitch_bend_from_norm(0.0), 8192);
+        assert_eq!(pitch_bend_from_norm(1.0), 16383);
+    }
+
+    #[test]
+    fn smoothing_approaches_the_target() {
+        let config = ControllerConfig {
+            pitch_smoothing: PitchSmoothing {
+                time_constant_ms: 20.0,
+                max_slew_per_ms: 0.0,
+            },
+            ..ControllerConfig::default()
+        };
+        let buttons = ButtonMapper::new();
+        let mut pitch = PitchState::new();
+        pitch.set_input(1.0, false);
+        let first = pitch.tick(&config, &buttons, 10.0).expect("moved");
+        assert!(first > 8192 && first < 16383);
+        let second = pitch.tick(&config, &buttons, 10.0).expect("moved");
+        assert!(second > first && second < 16383);
+    }
+
+    #[test]
+    fn letting_go_glides_back_over_the_release_time() {
+        let config = ControllerConfig {
+            pitch_release: PitchRelease {
+                time_ms: 100.0,
+                curve: ResponseCurve::Linear,
+            },
+            ..ControllerConfig::default()
+        };
+        let buttons = ButtonMapper::new();
+        let mut pitch = PitchState::new();
+        pitch.set_input(1.0, false);
+        assert_eq!(pitch.tick(&config, &buttons, 10.0), Some(16383));
+        pitch.set_input(0.0, true);
+        let halfway = pitch.tick(&config, &buttons, 50.0).expect("moved");
+        assert!(halfway.abs_diff(12288) <= 1);
+        assert_eq!(pitch.tick(&config, &buttons, 60.0), Some(8192));
+    }
+
+    #[test]
+    fn reset_sends_center_on_the_next_tick() {
+        let config = ControllerConfig {
+            pitch_smoothing: PitchSmoothing {
+                time_constant_ms: 50.0,
+                max_slew_per_ms: 0.0,
+            },
+            pitch_quantize: PitchQuantize::Semitones,
+            pitch_glide_ms: 100.0,
+            ..ControllerConfig::default()
+        };
+        let buttons = ButtonMapper::new();
+        let mut pitch = PitchState::new();
+        pitch.set_input(1.0, false);
+        assert!(pitch.tick(&config, &buttons, 10.0).is_some());
+        pitch.reset();
+        assert_eq!(pitch.tick(&config, &buttons, 10.0), Some(8192));
+        assert_eq!(pitch.tick(&config, &buttons, 10.0), None);
+    }
+
+    #[test]
+    fn a_held_bend_ignores_the_stick_until_let_go() {
+        let config = ControllerConfig {
+            buttons: vec![ButtonBinding {
+                button: Button::Back,
+                action: ButtonAction::Internal {
+                    action: InternalAction::HoldBend,
+                },
+                rumble: None,
+                velocity_source: None,
+            }],
+            ..ControllerConfig::default()
+        };
+        let mut buttons = ButtonMapper::new();
+        let mut pitch = PitchState::new();
+        pitch.set_input(1.0, false);
+        assert_eq!(pitch.tick(&config, &buttons, 10.0), Some(16383));
+        buttons.press(&config, Button::Back, &VelocityTracker::new());
+        pitch.set_input(-1.0, false);
+        assert_eq!(pitch.tick(&config, &buttons, 10.0), None);
+        assert!(pitch.is_frozen());
+        buttons.release(Button::Back);
+        assert_eq!(pitch.tick(&config, &buttons, 10.0), Some(0));
+    }
+}
@@ -0,0 +1,669 @@
+use crate::calibration::DeviceCalibration;
+use crate::controller::ControllerConfig;
+use crate::keyboard::KeyboardConfig;
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml_edit::ser::ValueSerializer;
+use toml_edit::{DocumentMut, Item};
+
+const APP_DIR: &str = "pitch_controller";
+const PROFILE_FILE: &str = "profile.toml";
+
+/// Everything persisted in the profile file.
+///
+/// The top-level keys are the default `ControllerConfig`, used by every controller
+/// without an entry in `devices`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(flatten)]
+    pub default: ControllerConfig,
+    /// Per-device axis calibration, applied to raw values before any other processing.
+    pub calibrations: Vec<DeviceCalibration>,
+    pub devices: Vec<DeviceProfile>,
+    /// Controllers to open. All of them if omitted.
+    pub enabled_controllers: Option<Vec<EnabledController>>,
+    /// Most pitch bend messages per second on each MIDI channel, 0 for no cap.
+    pub max_bend_rate: u32,
+    /// Computer keyboard and mouse as a stand-in controller.
+    pub keyboard: KeyboardConfig,
+}
+
+/// A config of its own for one kind of controller (or one unit, with `slot`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub guid: String,
+    /// Only match the Nth attached controller with this GUID (0 = first), so two
+    /// identical pads can play on different channels. Matches all of them if omitted.
+    #[serde(default)]
+    pub slot: Option<usize>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(flatten)]
+    pub config: ControllerConfig,
+}
+
+impl DeviceProfile {
+    pub fn matches(&self, guid: &str, slot: usize) -> bool {
+        self.guid == guid && self.slot.unwrap_or(slot) == slot
+    }
+}
+
+/// An entry of `Profile::enabled_controllers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EnabledController {
+    /// A controller name or GUID, enabling every unit of it.
+    Any(String),
+    /// One kind of controller, or only its Nth unit with `slot` as in `DeviceProfile`.
+    Unit {
+        guid: String,
+        #[serde(default)]
+        slot: Option<usize>,
+    },
+}
+
+impl EnabledController {
+    pub fn matches(&self, name: &str, guid: &str, slot: usize) -> bool {
+        match self {
+            EnabledController::Any(s) => s == name || s == guid,
+            EnabledController::Unit { guid: g, slot: s } => g == guid && s.unwrap_or(slot) == slot,
+        }
+    }
+}
+
+impl Profile {
+    pub fn wants_controller(&self, name: &str, guid: &str, slot: usize) -> bool {
+        match &self.enabled_controllers {
+            Some(list) => list.iter().any(|e| e.matches(name, guid, slot)),
+            None => true,
+        }
+    }
+
+    pub fn device_profile(&self, guid: &str, slot: usize) -> Option<&DeviceProfile> {
+        self.devices.iter().find(|d| d.matches(guid, slot))
+    }
+
+    /// The config a controller plays with: its own entry in `devices`, else the default.
+    pub fn config_for(&self, guid: &str, slot: usize) -> &ControllerConfig {
+        self.device_profile(guid, slot)
+            .map_or(&self.default, |d| &d.config)
+    }
+
+    pub fn config_for_mut(&mut self, guid: &str, slot: usize) -> &mut ControllerConfig {
+        match self.devices.iter().position(|d| d.matches(guid, slot)) {
+            Some(i) => &mut self.devices[i].config,
+            None => &mut self.default,
+        }
+    }
+
+    /// The calibration of the unit in `slot`, else one for every controller with `guid`.
+    pub fn calibration_for(&self, guid: &str, slot: usize) -> Option<&DeviceCalibration> {
+        let find = |slot: Option<usize>| {
+            self.calibrations
+                .iter()
+                .find(|c| c.guid == guid && c.slot == slot)
+        };
+        find(Some(slot)).or_else(|| find(None))
+    }
+
+    /// Store `calibration`, replacing any earlier one for the same GUID and slot.
+    pub fn set_calibration(&mut self, calibration: DeviceCalibration) {
+        self.calibrations
+            .retain(|c| (&c.guid, c.slot) != (&calibration.guid, calibration.slot));
+        self.calibrations.push(calibration);
+    }
+
+    /// Remove the calibration of the unit in `slot`, keeping any GUID-wide one.
+    pub fn clear_calibration(&mut self, guid: &str, slot: usize) {
+        self.calibrations
+            .retain(|c| !(c.guid == guid && c.slot == Some(slot)));
+    }
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::Io { path, source } => {
+                write!(f, "can't access profile {}: {}", path.display(), source)
+            }
+            ProfileError::Parse { path, source } => {
+                write!(f, "invalid profile {}: {}", path.display(), source)
+            }
+            ProfileError::Serialize(e) => write!(f, "can't serialize profile: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+/// `$XDG_CONFIG_HOME/pitch_controller/profile.toml` (or the platform equivalent).
+pub fn default_profile_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR).join(PROFILE_FILE))
+}
+
+pub fn load_profile(path: &Path) -> Result<Profile, ProfileError> {
+    let text = fs::read_to_string(path).map_err(|source| ProfileError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    toml::from_str(&text).map_err(|source| ProfileError::Parse {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Like `load_profile`, but a missing file yields the default profile so a fresh
+/// install (or a new `--profile` path) starts up and gets created on first save.
+pub fn load_profile_or_default(path: &Path) -> Result<Profile, ProfileError> {
+    match load_profile(path) {
+        Err(ProfileError::Io { source, .. }) if source.kind() == io::ErrorKind::NotFound => {
+            Ok(Profile::default())
+        }
+        other => other,
+    }
+}
+
+pub fn save_profile(path: &Path, profile: &Profile) -> Result<(), ProfileError> {
+    let text = toml::to_string_pretty(profile).map_err(ProfileError::Serialize)?;
+    write_profile(path, &text)
+}
+
+/// Rewrite only the `enabled_controllers` key of the profile file, keeping the rest
+/// of it (comments, and anything edited but not saved in the GUI) as it is on disk.
+pub fn save_enabled_controllers(
+    path: &Path,
+    enabled: Option<&[EnabledController]>,
+) -> Result<(), ProfileError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(source) if source.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(source) => {
+            return Err(ProfileError::Io {
+                path: path.to_path_buf(),
+                source,
+            })
+        }
+    };
+    let mut doc: DocumentMut = text.parse().map_err(|e| ProfileError::Parse {
+        path: path.to_path_buf(),
+        source: toml::de::Error::custom(e),
+    })?;
+    match enabled {
+        Some(list) => {
+            let value = list
+                .serialize(ValueSerializer::new())
+                .map_err(|e| ProfileError::Serialize(toml::ser::Error::custom(e)))?;
+            doc["enabled_controllers"] = Item::Value(value);
+        }
+        None => {
+            doc.remove("enabled_controllers");
+        }
+    }
+    write_profile(path, &doc.to_string())
+}
+
+fn write_profile(path: &Path, text: &str) -> Result<(), ProfileError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|source| ProfileError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+    }
+    fs::write(path, text).map_err(|source| ProfileError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+/// Serde adapter storing a `Button` under SDL's mapping-string name ("a", "leftshoulder", ...).
+pub(crate) mod button_name {
+    use crate::controller::known_buttons;
+    use sdl2::controller::Button;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(button: &Button, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&button.string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Button, D::Error> {
+        let name = String::deserialize(d)?;
+        Button::from_string(&name).ok_or_else(|| {
+            let expected: Vec<String> = known_buttons().into_iter().map(|b| b.string()).collect();
+            D::Error::custom(format!(
+                "unknown button \"{}\", expected one of: {}",
+                name,
+                expected.join(", ")
+            ))
+        })
+    }
+}
+
+/// Like `button_name`, for a list of buttons.
+pub(crate) mod button_names {
+    use sdl2::controller::Button;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(buttons: &[Button], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(buttons.iter().map(|b| b.string()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Button>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::button_name")] Button);
+        Ok(Vec::<Wrapper>::deserialize(d)?
+            .into_iter()
+            .map(|Wrapper(button)| button)
+            .collect())
+    }
+}
+
+/// Serde adapter storing an `Axis` under SDL's mapping-string name ("leftx", "righttrigger", ...).
+pub(crate) mod axis_name {
+    use crate::controller::known_axes;
+    use sdl2::controller::Axis;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(axis: &Axis, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&axis.string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Axis, D::Error> {
+        let name = String::deserialize(d)?;
+        Axis::from_string(&name).ok_or_else(|| {
+            let expected: Vec<String> = known_axes().into_iter().map(|a| a.string()).collect();
+            D::Error::custom(format!(
+                "unknown axis \"{}\", expected one of: {}",
+                name,
+                expected.join(", ")
+            ))
+        })
+    }
+}
+
+/// Like `axis_name`, for an axis that may be left unset.
+pub(crate) mod optional_axis_name {
+    use sdl2::controller::Axis;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(axis: &Option<Axis>, s: S) -> Result<S::Ok, S::Error> {
+        match axis {
+            Some(axis) => super::axis_name::serialize(axis, s),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Axis>, D::Error> {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::axis_name")] Axis);
+        Ok(Option::<Wrapper>::deserialize(d)?.map(|Wrapper(axis)| axis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calibration::AxisCalibration;
+    use crate::controller::{BendDirection, PitchAxisMode};
+    use crate::curve::ResponseCurve;
+    use crate::keyboard::KeyboardMode;
+    use crate::lfo::{LfoConfig, LfoShape, LfoTarget};
+    use crate::mapping::{
+        AxisBinding, AxisPolarity, ButtonAction, ButtonBinding, CcMode, InternalAction, Layer,
+        LayerMode, ModulationTarget, Stick, StickBinding,
+    };
+    use crate::melodic::MelodicConfig;
+    use crate::motion::{MotionBinding, MotionSource};
+    use crate::pitch::{PitchQuantize, PitchRelease, PitchSmoothing, Scale};
+    use crate::rumble::{RumbleConfig, RumblePulse};
+    use crate::touchpad::{TouchAxis, TouchMode, TouchpadBinding};
+    use crate::velocity::{VelocityRange, VelocitySource};
+    use sdl2::controller::{Axis, Button};
+
+    /// A config with every kind of binding and every optional part set.
+    fn full_config() -> ControllerConfig {
+        let action = |button, action| ButtonBinding {
+            button,
+            action,
+            rumble: None,
+            velocity_source: None,
+        };
+        ControllerConfig {
+            channel: 3,
+            buttons: vec![
+                ButtonBinding {
+                    button: Button::A,
+                    action: ButtonAction::Note {
+                        note: 60,
+                        velocity: 90,
+                        channel: Some(1),
+                    },
+                    rumble: Some(RumblePulse {
+                        strength: 0.5,
+                        duration_ms: 40,
+                    }),
+                    velocity_source: Some(VelocitySource::StickSpeed {
+                        stick: Stick::Left,
+                        full_speed: 8.0,
+                        range: VelocityRange {
+                            min: 20,
+                            max: 120,
+                            curve: ResponseCurve::Linear,
+                        },
+                    }),
+                },
+                action(
+                    Button::X,
+                    ButtonAction::ControlChange {
+                        controller: 64,
+                        on: 127,
+                        off: 0,
+                        mode: CcMode::Toggle,
+                        channel: None,
+                    },
+                ),
+                action(
+                    Button::B,
+                    ButtonAction::Chord {
+                        notes: Vec::new(),
+                        root: 57,
+                        intervals: vec![3, 7],
+                        velocity: 70,
+                        channel: None,
+                        strum_ms: 25,
+                    },
+                ),
+                action(
+                    Button::Y,
+                    ButtonAction::ProgramChange {
+                        program: 5,
+                        channel: Some(4),
+                    },
+                ),
+                action(
+                    Button::Back,
+                    ButtonAction::Internal {
+                        action: InternalAction::ResetPitchBend,
+                    },
+                ),
+            ],
+            axes: vec![AxisBinding {
+                axis: Axis::TriggerLeft,
+                controller: 11,
+                channel: Some(5),
+                min: 5,
+                max: 120,
+                invert: true,
+                deadzone: 1500,
+                curve: ResponseCurve::Breakpoints {
+                    points: vec![[0.0, 0.0], [0.5, 0.25], [1.0, 1.0]],
+                },
+                polarity: Some(AxisPolarity::Unipolar),
+            }],
+            sticks: vec![StickBinding {
+                stick: Stick::Right,
+                deadzone: 3000,
+                radius: Some(ModulationTarget::ControlChange {
+                    controller: 1,
+                    channel: None,
+                    min: 0,
+                    max: 127,
+                    curve: ResponseCurve::Linear,
+                }),
+                angle: Some(ModulationTarget::PitchBend),
+            }],
+            motion: vec![MotionBinding {
+                source: MotionSource::TiltSide,
+                range_deg: -30.0,
+                deadzone_deg: 3.0,
+                target: ModulationTarget::PitchBend,
+            }],
+            touchpad: vec![TouchpadBinding {
+                finger: 1,
+                axis: TouchAxis::Y,
+                mode: TouchMode::Relative,
+                sensitivity: -0.5,
+                deadzone: 0.1,
+                target: ModulationTarget::ControlChange {
+                    controller: 74,
+                    channel: Some(2),
+                    min: 10,
+                    max: 100,
+                    curve: ResponseCurve::Linear,
+                },
+            }],
+            layers: vec![Layer {
+                name: "Drums".to_string(),
+                modifier: Button::LeftShoulder,
+                mode: LayerMode::Toggle,
+                buttons: vec![action(
+                    Button::A,
+                    ButtonAction::Note {
+                        note: 36,
+                        velocity: 127,
+                        channel: Some(9),
+                    },
+                )],
+                axes: Vec::new(),
+            }],
+            melodic: Some(MelodicConfig {
+                key: 2,
+                scale: Scale::Dorian,
+                octave: 3,
+                velocity: 110,
+                velocity_source: Some(VelocitySource::Axis {
+                    axis: Axis::TriggerRight,
+                    range: VelocityRange {
+                        min: 1,
+                        max: 127,
+                        curve: ResponseCurve::SCurve { amount: 0.3 },
+                    },
+                }),
+                degrees: vec![Button::A, Button::B, Button::X],
+                octave_up: Button::DPadUp,
+                octave_down: Button::DPadDown,
+                transpose_up: Button::Start,
+                transpose_down: Button::Back,
+            }),
+            pitch_axis: Axis::RightX,
+            pitch_axis_mode: PitchAxisMode::Unipolar {
+                direction: BendDirection::Down,
+            },
+            invert_pitch: false,
+            deadzone: 1000,
+            pitch_curve: ResponseCurve::Exponential { amount: 0.75 },
+            pitch_smoothing: PitchSmoothing {
+                time_constant_ms: 12.0,
+                max_slew_per_ms: 40.0,
+            },
+            pitch_quantize: PitchQuantize::Scale {
+                key: 9,
+                scale: Scale::MinorPentatonic,
+            },
+            pitch_bend_range: 12.0,
+            pitch_bend_up: Some(2.0),
+            pitch_bend_down: Some(12.0),
+            pitch_glide_ms: 40.0,
+            pitch_release: PitchRelease {
+                time_ms: 150.0,
+                curve: ResponseCurve::SCurve { amount: 0.5 },
+            },
+            lfo: Some(LfoConfig {
+                shape: LfoShape::Triangle,
+                target: LfoTarget::ControlChange { controller: 1 },
+                depth: 20.0,
+                depth_axis: Some(Axis::LeftY),
+                rate_hz: 4.0,
+                max_rate_hz: 9.0,
+                rate_axis: None,
+                sync_beats: Some(0.5),
+            }),
+            rumble: RumbleConfig {
+                bend_strength: 0.3,
+                step_tick: Some(RumblePulse {
+                    strength: 1.0,
+                    duration_ms: 15,
+                }),
+            },
+        }
+    }
+
+    fn full_profile() -> Profile {
+        Profile {
+            default: full_config(),
+            calibrations: vec![DeviceCalibration {
+                guid: "030000005e040000130b000011050000".to_string(),
+                slot: Some(1),
+                name: "Xbox Series X Controller".to_string(),
+                axes: vec![AxisCalibration {
+                    axis: Axis::LeftX,
+                    min: -31000,
+                    center: 400,
+                    max: 32000,
+                }],
+            }],
+            devices: vec![DeviceProfile {
+                guid: "030000004c050000cc09000011810000".to_string(),
+                slot: Some(1),
+                name: "DualShock 4".to_string(),
+                config: ControllerConfig {
+                    channel: 9,
+                    ..full_config()
+                },
+            }],
+            enabled_controllers: Some(vec![
+                EnabledController::Any("Xbox Series X Controller".to_string()),
+                EnabledController::Unit {
+                    guid: "030000004c050000cc09000011810000".to_string(),
+                    slot: Some(1),
+                },
+            ]),
+            max_bend_rate: 200,
+            keyboard: KeyboardConfig {
+                mode: KeyboardMode::Always,
+                channel: 2,
+                velocity: 80,
+                base_note: 48,
+                keys: vec!["Q".to_string(), "Semicolon".to_string()],
+                octave_down: "Minus".to_string(),
+                octave_up: "Equals".to_string(),
+                drag_range_px: 200.0,
+                wheel_range_px: 300.0,
+            },
+        }
+    }
+
+    #[test]
+    fn a_full_profile_survives_a_round_trip() {
+        let text = toml::to_string_pretty(&full_profile()).expect("serialize");
+        let loaded: Profile = toml::from_str(&text).expect("deserialize");
+        let again = toml::to_string_pretty(&loaded).expect("serialize again");
+        assert_eq!(text, again);
+
+        assert_eq!(loaded.default.channel, 3);
+        assert_eq!(loaded.default.buttons, full_config().buttons);
+        assert_eq!(loaded.default.axes, full_config().axes);
+        assert_eq!(loaded.default.pitch_axis, Axis::RightX);
+        assert!(!loaded.default.invert_pitch);
+        assert_eq!(loaded.default.deadzone, 1000);
+        assert_eq!(
+            loaded.default.pitch_smoothing,
+            full_config().pitch_smoothing
+        );
+        assert_eq!(loaded.default.pitch_quantize, full_config().pitch_quantize);
+        assert_eq!(loaded.default.pitch_bend_up, Some(2.0));
+        assert_eq!(loaded.default.pitch_bend_down, Some(12.0));
+        assert_eq!(loaded.default.pitch_release, full_config().pitch_release);
+        assert_eq!(loaded.devices[0].slot, Some(1));
+        assert_eq!(loaded.devices[0].config.channel, 9);
+        assert_eq!(loaded.calibrations, full_profile().calibrations);
+        assert_eq!(
+            loaded.enabled_controllers,
+            full_profile().enabled_controllers
+        );
+        assert_eq!(loaded.max_bend_rate, 200);
+    }
+
+    #[test]
+    fn an_empty_file_is_the_default_profile() {
+        let loaded: Profile = toml::from_str("").expect("deserialize");
+        let default = ControllerConfig::default();
+        assert_eq!(loaded.default.channel, default.channel);
+        assert_eq!(loaded.default.buttons, default.buttons);
+        assert_eq!(loaded.default.pitch_axis, default.pitch_axis);
+        assert_eq!(loaded.default.deadzone, default.deadzone);
+        assert!(loaded.devices.is_empty());
+        assert_eq!(loaded.enabled_controllers, None);
+    }
+
+    #[test]
+    fn saving_enabled_controllers_keeps_the_rest_of_the_file() {
+        let dir = std::env::temp_dir().join(format!("pitch_controller_{}", std::process::id()));
+        let path = dir.join(PROFILE_FILE);
+        let original = "# my live setup\nchannel = 2 # drums\n\n[[devices]]\nguid = \"pad\"\n";
+        fs::create_dir_all(&dir).expect("create dir");
+        fs::write(&path, original).expect("write");
+
+        let enabled = [EnabledController::Unit {
+            guid: "pad".to_string(),
+            slot: Some(1),
+        }];
+        save_enabled_controllers(&path, Some(&enabled)).expect("save");
+        let text = fs::read_to_string(&path).expect("read");
+        assert!(text.contains("# my live setup"));
+        assert!(text.contains("channel = 2 # drums"));
+        let loaded = load_profile(&path).expect("load");
+        assert_eq!(loaded.enabled_controllers.as_deref(), Some(&enabled[..]));
+        assert_eq!(loaded.default.channel, 2);
+        assert_eq!(loaded.devices.len(), 1);
+
+        save_enabled_controllers(&path, None).expect("save");
+        assert_eq!(fs::read_to_string(&path).expect("read"), original);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_unit_calibration_wins_over_the_guid_wide_one() {
+        let calibration = |slot| DeviceCalibration {
+            guid: "pad".to_string(),
+            slot,
+            name: String::new(),
+            axes: Vec::new(),
+        };
+        let mut profile = Profile {
+            calibrations: vec![calibration(None), calibration(Some(1))],
+            ..Profile::default()
+        };
+        let slot_of =
+            |profile: &Profile, slot| profile.calibration_for("pad", slot).map(|c| c.slot);
+        assert_eq!(slot_of(&profile, 0), Some(None));
+        assert_eq!(slot_of(&profile, 1), Some(Some(1)));
+        assert_eq!(profile.calibration_for("other", 0), None);
+
+        // Clearing a unit falls back to the shared one, which stays
+        profile.clear_calibration("pad", 1);
+        assert_eq!(slot_of(&profile, 1), Some(None));
+        profile.clear_calibration("pad", 0);
+        assert_eq!(profile.calibrations.len(), 1);
+    }
+}
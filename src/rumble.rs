@@ -0,0 +1,164 @@
+use sdl2::controller::GameController;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// How long each rumble request lasts. Continuous rumble is renewed well before it
+/// runs out, so a stalled app stops shaking soon after.
+const RUMBLE_REQUEST_MS: u32 = 100;
+const RUMBLE_RENEW: Duration = Duration::from_millis(50);
+
+/// A short buzz on the high-frequency motor.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RumblePulse {
+    /// `0.0..=1.0`
+    pub strength: f32,
+    pub duration_ms: u32,
+}
+
+/// Haptic feedback tied to the pitch bend. Note pulses are set per button binding.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RumbleConfig {
+    /// Low-frequency rumble at full bend, scaled down with the bend amount. 0 is off.
+    pub bend_strength: f32,
+    /// Tick whenever a quantized bend lands on a new step.
+    pub step_tick: Option<RumblePulse>,
+}
+
+/// Drives one controller's motors from pulses and the continuous bend rumble.
+#[derive(Debug)]
+pub(crate) struct Rumble {
+    pulse: Option<(f32, Instant)>,
+    continuous: f32,
+    /// Motor levels of the last request and when it was made.
+    sent: Option<((u16, u16), Instant)>,
+    /// Cleared after the first failed request; pads without rumble refuse every one.
+    supported: bool,
+}
+
+impl Rumble {
+    pub fn new() -> Self {
+        Self {
+            pulse: None,
+            continuous: 0.0,
+            sent: None,
+            supported: true,
+        }
+    }
+
+    pub fn pulse(&mut self, pulse: &RumblePulse, now: Instant) {
+        let until = now + Duration::from_millis(pulse.duration_ms as u64);
+        self.pulse = Some((pulse.strength.clamp(0.0, 1.0), until));
+    }
+
+    /// Set the continuous low-frequency level, `0.0..=1.0`.
+    pub fn set_continuous(&mut self, strength: f32) {
+        self.continuous = strength.clamp(0.0, 1.0);
+    }
+
+    pub fn update(&mut self, controller: &mut GameController, now: Instant) {
+        let Some((levels, duration)) = self.request(now) else {
+            return;
+        };
+        match controller.set_rumble(levels.0, levels.1, duration) {
+            Ok(()) => self.sent = Some((levels, now)),
+            Err(e) => {
+                println!("Rumble not available: {}", e);
+                self.supported = false;
+            }
+        }
+    }
+
+    /// Motor levels and duration to request, if they changed or a running rumble is
+    /// due for renewal.
+    fn request(&mut self, now: Instant) -> Option<((u16, u16), u32)> {
+        if !self.supported {
+            return None;
+        }
+        if self.pulse.is_some_and(|(_, until)| now >= until) {
+            self.pulse = None;
+        }
+        let high = self.pulse.map_or(0.0, |(strength, _)| strength);
+        let levels = (motor_level(self.continuous), motor_level(high));
+
+        let renew = match self.sent {
+            Some((sent, at)) => sent != levels || (levels != (0, 0) && now - at >= RUMBLE_RENEW),
+            None => levels != (0, 0),
+        };
+        if !renew {
+            return None;
+        }
+        let duration = if levels == (0, 0) {
+            0
+        } else {
+            RUMBLE_REQUEST_MS
+        };
+        Some((levels, duration))
+    }
+}
+
+fn motor_level(strength: f32) -> u16 {
+    (strength.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The request made now, taken as sent.
+    fn send(rumble: &mut Rumble, now: Instant) -> Option<((u16, u16), u32)> {
+        let request = rumble.request(now);
+        if let Some((levels, _)) = request {
+            rumble.sent = Some((levels, now));
+        }
+        request
+    }
+
+    #[test]
+    fn nothing_is_sent_while_idle() {
+        let mut rumble = Rumble::new();
+        assert_eq!(send(&mut rumble, Instant::now()), None);
+    }
+
+    #[test]
+    fn continuous_rumble_is_renewed_before_it_runs_out() {
+        let start = Instant::now();
+        let mut rumble = Rumble::new();
+        rumble.set_continuous(1.0);
+        let full = ((u16::MAX, 0), RUMBLE_REQUEST_MS);
+        assert_eq!(send(&mut rumble, start), Some(full));
+        assert_eq!(send(&mut rumble, start + Duration::from_millis(10)), None);
+        assert_eq!(send(&mut rumble, start + RUMBLE_RENEW), Some(full));
+
+        // Stopping is sent once, with no duration
+        rumble.set_continuous(0.0);
+        let later = start + RUMBLE_RENEW * 2;
+        assert_eq!(send(&mut rumble, later), Some(((0, 0), 0)));
+        assert_eq!(send(&mut rumble, later + RUMBLE_RENEW), None);
+    }
+
+    #[test]
+    fn a_pulse_ends_after_its_duration() {
+        let start = Instant::now();
+        let mut rumble = Rumble::new();
+        let pulse = RumblePulse {
+            strength: 0.5,
+            duration_ms: 30,
+        };
+        rumble.pulse(&pulse, start);
+        assert_eq!(
+            send(&mut rumble, start),
+            Some(((0, motor_level(0.5)), RUMBLE_REQUEST_MS))
+        );
+        let end = start + Duration::from_millis(30);
+        assert_eq!(send(&mut rumble, end), Some(((0, 0), 0)));
+    }
+
+    #[test]
+    fn unsupported_pads_are_left_alone() {
+        let mut rumble = Rumble::new();
+        rumble.supported = false;
+        rumble.set_continuous(1.0);
+        assert_eq!(send(&mut rumble, Instant::now()), None);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::controller::{apply_deadzone, ControllerConfig};
+use crate::events::ControllerEvent;
+use crate::mapping::{cc_from_norm, ModulationTarget};
+use sdl2::controller::GameController;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Most fingers read from a touchpad; DualShock 4 and DualSense track two.
+const MAX_FINGERS: usize = 4;
+
+/// A direction across the touchpad. Right and up are positive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TouchAxis {
+    X,
+    Y,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TouchMode {
+    /// The spot on the pad is the value; the middle is the center of the range.
+    #[default]
+    Absolute,
+    /// Sliding moves the value from where it was, like an endless knob. A pitch bend
+    /// starts from center wherever the finger lands.
+    Relative,
+}
+
+/// Routes one finger's position along one direction of the touchpad to pitch bend or a CC.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TouchpadBinding {
+    /// 0 is the first finger down, 1 the second, ...
+    #[serde(default)]
+    pub finger: usize,
+    pub axis: TouchAxis,
+    #[serde(default)]
+    pub mode: TouchMode,
+    /// Relative mode: how far the value moves per pad width, in full ranges. Negative
+    /// flips the direction.
+    #[serde(default = "default_sensitivity")]
+    pub sensitivity: f32,
+    /// Absolute mode: ignored area around the middle, as a fraction of half the pad.
+    #[serde(default)]
+    pub deadzone: f32,
+    pub target: ModulationTarget,
+}
+
+fn default_sensitivity() -> f32 {
+    1.0
+}
+
+impl fmt::Display for TouchpadBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Finger {} {:?} ({:?}) → {}",
+            self.finger + 1,
+            self.axis,
+            self.mode,
+            self.target
+        )
+    }
+}
+
+/// Position of a finger on the pad, `0.0..=1.0` from the left and from the top, as
+/// SDL reports it.
+pub type TouchPoint = (f32, f32);
+
+/// Fingers on one controller's touchpad, mapped according to `ControllerConfig::touchpad`.
+#[derive(Debug, Default)]
+pub struct TouchpadState {
+    fingers: Vec<Option<TouchPoint>>,
+    /// `fingers` before the last change, for relative bindings.
+    previous: Vec<Option<TouchPoint>>,
+    /// Accumulated value of each relative binding, by binding index.
+    relative: HashMap<usize, f32>,
+    last_sent: HashMap<(u8, u8), u8>,
+}
+
+impl TouchpadState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the fingers on the pad's first touchpad. Returns whether any finger
+    /// landed, moved or lifted since the last call.
+    pub fn poll(&mut self, controller: &GameController) -> bool {
+        self.set_fingers(read_fingers(controller))
+    }
+
+    fn set_fingers(&mut self, fingers: Vec<Option<TouchPoint>>) -> bool {
+        if fingers == self.fingers {
+            return false;
+        }
+        self.previous = std::mem::replace(&mut self.fingers, fingers);
+        true
+    }
+
+    /// Finger slots of the touchpad, `None` where no finger is down.
+    pub fn fingers(&self) -> &[Option<TouchPoint>] {
+        &self.fingers
+    }
+
+    /// Returns the Control Changes to send and, if a binding drives the pitch, the bend
+    /// input in `-1.0..=1.0` and whether its finger is off the pad.
+    pub fn map(
+        &mut self,
+        config: &ControllerConfig,
+    ) -> (Vec<ControllerEvent>, Option<(f32, bool)>) {
+        let mut out = Vec::new();
+        let mut pitch: Option<(f32, bool)> = None;
+        for (i, binding) in config.touchpad.iter().enumerate() {
+            let finger =
+                |fingers: &[Option<TouchPoint>]| fingers.get(binding.finger).copied().flatten();
+            let (before, now) = (finger(&self.previous), finger(&self.fingers));
+            // 0 at the left or bottom edge, 1 at the right or top
+            let along = |(x, y): TouchPoint| match binding.axis {
+                TouchAxis::X => x,
+                TouchAxis::Y => 1.0 - y,
+            };
+            let norm = match binding.mode {
+                TouchMode::Absolute => {
+                    now.map(|p| apply_deadzone(along(p) * 2.0 - 1.0, binding.deadzone))
+                }
+                TouchMode::Relative => {
+                    let value = self.relative.entry(i).or_insert(0.0);
+                    match (before, now) {
+                        (Some(before), Some(now)) => {
+                            let delta = (along(now) - along(before)) * 2.0 * binding.sensitivity;
+                            *value = (*value + delta).clamp(-1.0, 1.0);
+                        }
+                        (None, Some(_)) if binding.target == ModulationTarget::PitchBend => {
+                            *value = 0.0;
+                        }
+                        _ => {}
+                    }
+                    now.map(|_| *value)
+                }
+            };
+            match &binding.target {
+                ModulationTarget::ControlChange {
+                    controller,
+                    channel,
+                    min,
+                    max,
+                    curve,
+                } => {
+                    // A lifted finger leaves the CC where it was
+                    let Some(norm) = norm else {
+                        continue;
+                    };
+                    let channel = channel.unwrap_or(config.channel);
+                    let cc = cc_from_norm(curve.apply(norm), *min, *max);
+                    if self.last_sent.insert((channel, *controller), cc) != Some(cc) {
+                        out.push(ControllerEvent::ControlChange {
+                            channel,
+                            controller: *controller,
+                            value: cc,
+                        });
+                    }
+                }
+                ModulationTarget::PitchBend => {
+                    // With several bend bindings, a finger on the pad wins over a lifted one
+                    if pitch.is_some_and(|(_, resting)| !resting) {
+                        continue;
+                    }
+                    pitch = Some(match norm {
+                        Some(norm) => (norm, false),
+                        None => (0.0, true),
+                    });
+                }
+            }
+        }
+        (out, pitch)
+    }
+}
+
+/// Finger slots of the pad's first touchpad, empty on pads without one.
+///
+/// sdl2 0.35 neither wraps the touchpad functions nor passes the touchpad events on,
+/// so the state is read directly.
+fn read_fingers(controller: &GameController) -> Vec<Option<TouchPoint>> {
+    unsafe {
+        let raw = sdl2::sys::SDL_GameControllerFromInstanceID(controller.instance_id() as i32);
+        if raw.is_null() || sdl2::sys::SDL_GameControllerGetNumTouchpads(raw) < 1 {
+            return Vec::new();
+        }
+        let count = sdl2::sys::SDL_GameControllerGetNumTouchpadFingers(raw, 0).max(0) as usize;
+        (0..count.min(MAX_FINGERS))
+            .map(|finger| {
+                let (mut down, mut x, mut y, mut pressure) = (0u8, 0.0f32, 0.0f32, 0.0f32);
+                let ok = sdl2::sys::SDL_GameControllerGetTouchpadFinger(
+                    raw,
+                    0,
+                    finger as i32,
+                    &mut down,
+                    &mut x,
+                    &mut y,
+                    &mut pressure,
+                ) == 0;
+                (ok && down != 0).then_some((x, y))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::ResponseCurve;
+
+    fn binding(mode: TouchMode, target: ModulationTarget) -> TouchpadBinding {
+        TouchpadBinding {
+            finger: 0,
+            axis: TouchAxis::X,
+            mode,
+            sensitivity: 1.0,
+            deadzone: 0.0,
+            target,
+        }
+    }
+
+    fn config(binding: TouchpadBinding) -> ControllerConfig {
+        ControllerConfig {
+            touchpad: vec![binding],
+            ..ControllerConfig::default()
+        }
+    }
+
+    fn cc_target() -> ModulationTarget {
+        ModulationTarget::ControlChange {
+            controller: 1,
+            channel: None,
+            min: 0,
+            max: 127,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    fn bend_at(pad: &mut TouchpadState, config: &ControllerConfig, x: Option<f32>) -> (f32, bool) {
+        pad.set_fingers(vec![x.map(|x| (x, 0.5))]);
+        pad.map(config).1.expect("pitch bound")
+    }
+
+    #[test]
+    fn absolute_follows_the_finger_and_lifting_recenters() {
+        let config = config(binding(TouchMode::Absolute, ModulationTarget::PitchBend));
+        let mut pad = TouchpadState::new();
+        assert_eq!(bend_at(&mut pad, &config, Some(0.75)), (0.5, false));
+        assert_eq!(bend_at(&mut pad, &config, Some(0.0)), (-1.0, false));
+        assert_eq!(bend_at(&mut pad, &config, None), (0.0, true));
+    }
+
+    #[test]
+    fn relative_bend_starts_from_center_where_the_finger_lands() {
+        let config = config(binding(TouchMode::Relative, ModulationTarget::PitchBend));
+        let mut pad = TouchpadState::new();
+        assert_eq!(bend_at(&mut pad, &config, Some(0.5)), (0.0, false));
+        assert_eq!(bend_at(&mut pad, &config, Some(0.75)), (0.5, false));
+        assert_eq!(bend_at(&mut pad, &config, None), (0.0, true));
+        // Lands far to the left, still no bend until it slides
+        assert_eq!(bend_at(&mut pad, &config, Some(0.1)), (0.0, false));
+    }
+
+    #[test]
+    fn relative_cc_picks_up_where_the_last_slide_ended() {
+        let config = config(binding(TouchMode::Relative, cc_target()));
+        let mut pad = TouchpadState::new();
+        let mut cc_at = |x: Option<f32>| {
+            pad.set_fingers(vec![x.map(|x| (x, 0.5))]);
+            match pad.map(&config).0[..] {
+                [ControllerEvent::ControlChange { value, .. }] => Some(value),
+                _ => None,
+            }
+        };
+        assert_eq!(cc_at(Some(0.5)), Some(64));
+        assert_eq!(cc_at(Some(0.75)), Some(95));
+        // Lifting leaves the CC alone
+        assert_eq!(cc_at(None), None);
+        assert_eq!(cc_at(Some(0.1)), None);
+        assert_eq!(cc_at(Some(0.35)), Some(127));
+    }
+}
@@ -1,27 +1,96 @@
-use crate::events::ControllerEvent;
+use crate::calibration::CalibrationRecorder;
+use crate::controller::{
+    apply_deadzone, known_axes, normalize_axis, normalize_unipolar, pitch_input, BendDirection,
+    ControllerConfig, PitchAxisMode,
+};
+use crate::curve::ResponseCurve;
+use crate::events::{ControllerCommand, ControllerEvent, DetectedDevice, DeviceId};
+use crate::keyboard::{KeyboardMode, KeyboardSource};
+use crate::lfo::{LfoConfig, LfoShape, LfoTarget};
+use crate::mapping::AxisPolarity;
+use crate::melodic::{MelodicConfig, MelodicShift};
 use crate::midi_graph::{MidiEndpoint, MidiEndpointId, MidiGraph};
+use crate::motion::MotionSource;
+use crate::pitch::{PitchQuantize, Scale};
+use crate::profile::{
+    save_enabled_controllers, save_profile, DeviceProfile, EnabledController, Profile,
+};
+use crate::rumble::{RumbleConfig, RumblePulse};
+use crate::touchpad::TouchPoint;
 use eframe::egui;
 use sdl2::controller::{Axis, Button};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpsc;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+/// Steps of the guided calibration of the selected controller.
+enum CalibrationStep {
+    Idle,
+    /// Waiting for the player to let go of everything.
+    Rest,
+    /// Recording extremes while the player moves every axis through its full range.
+    Extremes(CalibrationRecorder),
+}
+
+/// Latest state of one attached controller, as reported by the controller thread.
+struct DeviceView {
+    name: String,
+    guid: String,
+    slot: usize,
+    mapping: String,
+    buttons: Vec<Button>,
+    axes: Vec<Axis>,
+    button_states: HashMap<Button, bool>,
+    axis_states: HashMap<Axis, i16>,
+    /// Degrees from rest of each motion source, once the pad reports any.
+    motion_states: BTreeMap<MotionSource, f32>,
+    /// Touchpad finger slots, once the pad reports a touch.
+    touch: Vec<Option<TouchPoint>>,
+    /// Pitch bend frozen by a hold/latch button.
+    bend_held: bool,
+    /// Index of the active layer in the config's `layers`; `None` for the base one.
+    layer: Option<usize>,
+    /// Octave and key moved from the D-pad in melodic mode.
+    melodic: MelodicShift,
+}
+
+impl DeviceView {
+    /// Latest value of `axis` with the device's calibration applied.
+    fn calibrated_axis(&self, profile: &Profile, axis: Axis) -> Option<i16> {
+        let raw = *self.axis_states.get(&axis)?;
+        let calibration = profile.calibration_for(&self.guid, self.slot);
+        Some(calibration.map_or(raw, |c| c.apply(axis, raw)))
+    }
+
+    fn config<'a>(&self, profile: &'a Profile) -> &'a ControllerConfig {
+        profile.config_for(&self.guid, self.slot)
+    }
+}
+
 pub struct ControllerApp {
     controller_rx: mpsc::Receiver<ControllerEvent>,
     midi_tx: mpsc::Sender<ControllerEvent>,
+    command_tx: mpsc::Sender<ControllerCommand>,
+    /// Shared with the MIDI worker, which reads it for every pitch bend.
+    max_bend_rate: Arc<AtomicU32>,
     midi_graph: Arc<MidiGraph>,
-    last_pitch_bend: u16,
-    last_tilt: f32,
-    button_states: HashMap<Button, bool>,
-    axis_states: HashMap<Axis, i16>,
-    controller_name: Option<String>,
-    controller_mapping: Option<String>,
-    available_buttons: Vec<Button>,
-    available_axes: Vec<Axis>,
+    profile: Profile,
+    profile_path: Option<PathBuf>,
+    devices: BTreeMap<DeviceId, DeviceView>,
+    /// Every joystick SDL sees, including the ones not opened.
+    detected: Vec<DetectedDevice>,
+    selected_device: Option<DeviceId>,
+    /// Last pitch bend sent on each MIDI channel.
+    pitch_bends: HashMap<u8, u16>,
+    calibration: CalibrationStep,
     last_event_at: Option<Instant>,
+    /// Computer keyboard and mouse, playing while no controller is open.
+    keyboard: KeyboardSource,
     endpoints: Vec<MidiEndpoint>,
     selected_src: Option<usize>,
     selected_dst: Option<usize>,
@@ -58,25 +127,524 @@ fn configure_fonts(ctx: &egui::Context) {
     ctx.data_mut(|d| d.insert_temp(key, true));
 }
 
+/// Preset picker, parameter controls and a plot of the curve (input magnitude vs. output).
+/// `input` marks the live input on the plot. Returns true if the curve was edited.
+fn curve_editor(
+    ui: &mut egui::Ui,
+    id: &str,
+    curve: &mut ResponseCurve,
+    input: Option<f32>,
+) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source(id)
+            .selected_text(curve.to_string())
+            .show_ui(ui, |ui| {
+                for preset in ResponseCurve::presets() {
+                    let selected = std::mem::discriminant(&preset) == std::mem::discriminant(curve);
+                    if ui.selectable_label(selected, preset.to_string()).clicked() && !selected {
+                        *curve = preset;
+                        changed = true;
+                    }
+                }
+            });
+        match curve {
+            ResponseCurve::Exponential { amount }
+            | ResponseCurve::Logarithmic { amount }
+            | ResponseCurve::SCurve { amount } => {
+                changed |= ui
+                    .add(egui::Slider::new(amount, 0.0..=1.0).text("amount"))
+                    .changed();
+            }
+            ResponseCurve::Breakpoints { points } => {
+                if ui.button("Add point").clicked() {
+                    // Split the widest segment so the new point never overlaps an old one
+                    let widest = points
+                        .windows(2)
+                        .enumerate()
+                        .max_by(|(_, a), (_, b)| {
+                            (a[1][0] - a[0][0]).total_cmp(&(b[1][0] - b[0][0]))
+                        })
+                        .map(|(i, _)| i);
+                    if let Some(i) = widest {
+                        let (a, b) = (points[i], points[i + 1]);
+                        points.insert(i + 1, [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0]);
+                        changed = true;
+                    }
+                }
+                ui.label("drag points, right-click to remove");
+            }
+            ResponseCurve::Linear => {}
+        }
+    });
+    changed |= curve_plot(ui, curve, input);
+    changed
+}
+
+/// Turn one controller on or off in `enabled_controllers`. Entries that also cover
+/// other units are split up, so those stay as they were.
+fn set_enabled(
+    list: &mut Vec<EnabledController>,
+    detected: &[DetectedDevice],
+    device: &DetectedDevice,
+    enabled: bool,
+) {
+    let matches = |e: &EnabledController, d: &DetectedDevice| e.matches(&d.name, &d.guid, d.slot);
+    let unit = |d: &DetectedDevice| EnabledController::Unit {
+        guid: d.guid.clone(),
+        slot: Some(d.slot),
+    };
+    let mut others = Vec::new();
+    list.retain(|entry| {
+        if !matches(entry, device) {
+            return true;
+        }
+        others.extend(
+            detected
+                .iter()
+                .filter(|d| d.index != device.index && matches(entry, d))
+                .map(unit),
+        );
+        false
+    });
+    if enabled {
+        others.push(unit(device));
+    }
+    for entry in others {
+        if !list.contains(&entry) {
+            list.push(entry);
+        }
+    }
+}
+
+const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
+];
+
+/// "C4" for 60, with middle C in octave 4.
+fn note_name(note: i32) -> String {
+    format!(
+        "{}{}",
+        NOTE_NAMES[note.rem_euclid(12) as usize],
+        note.div_euclid(12) - 1
+    )
+}
+
+/// Bend range, quantization mode and glide of the pitch axis. Returns true if edited.
+fn quantize_editor(ui: &mut egui::Ui, config: &mut ControllerConfig) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Synth bend range (semitones)");
+        changed |= ui
+            .add(egui::DragValue::new(&mut config.pitch_bend_range).clamp_range(0.0..=48.0))
+            .changed();
+    });
+    let mut asymmetric = config.pitch_bend_up.is_some() || config.pitch_bend_down.is_some();
+    if ui
+        .checkbox(&mut asymmetric, "Separate up/down range")
+        .changed()
+    {
+        let range = asymmetric.then_some(config.pitch_bend_range);
+        config.pitch_bend_up = range;
+        config.pitch_bend_down = range;
+        changed = true;
+    }
+    if asymmetric {
+        // A profile may set only one direction, the other follows the synth's range.
+        // Edit copies so an unset direction stays unset until it is dragged.
+        let range = config.pitch_bend_range;
+        let mut up = config.pitch_bend_up.unwrap_or(range);
+        let mut down = config.pitch_bend_down.unwrap_or(range);
+        ui.horizontal(|ui| {
+            ui.label("Up");
+            if ui
+                .add(egui::DragValue::new(&mut up).clamp_range(0.0..=48.0))
+                .changed()
+            {
+                config.pitch_bend_up = Some(up);
+                changed = true;
+            }
+            ui.label("Down");
+            if ui
+                .add(egui::DragValue::new(&mut down).clamp_range(0.0..=48.0))
+                .changed()
+            {
+                config.pitch_bend_down = Some(down);
+                changed = true;
+            }
+        });
+    }
+    ui.horizontal(|ui| {
+        ui.label("Quantize");
+        let quantize = &mut config.pitch_quantize;
+        egui::ComboBox::from_id_source("pitch_quantize")
+            .selected_text(quantize.to_string())
+            .show_ui(ui, |ui| {
+                let modes = [
+                    PitchQuantize::Off,
+                    PitchQuantize::Semitones,
+                    PitchQuantize::Scale {
+                        key: 0,
+                        scale: Scale::Major,
+                    },
+                ];
+                for mode in modes {
+                    let selected =
+                        std::mem::discriminant(&mode) == std::mem::discriminant(quantize);
+                    if ui.selectable_label(selected, mode.to_string()).clicked() && !selected {
+                        *quantize = mode;
+                        changed = true;
+                    }
+                }
+            });
+        if let PitchQuantize::Scale { key, scale } = quantize {
+            egui::ComboBox::from_id_source("pitch_quantize_key")
+                .selected_text(NOTE_NAMES[(*key % 12) as usize])
+                .width(50.0)
+                .show_ui(ui, |ui| {
+                    for (i, name) in NOTE_NAMES.iter().enumerate() {
+                        changed |= ui.selectable_value(key, i as u8, *name).changed();
+                    }
+                });
+            egui::ComboBox::from_id_source("pitch_quantize_scale")
+                .selected_text(format!("{:?}", scale))
+                .show_ui(ui, |ui| {
+                    for candidate in Scale::all() {
+                        changed |= ui
+                            .selectable_value(scale, candidate, format!("{:?}", candidate))
+                            .changed();
+                    }
+                });
+        }
+    });
+    if config.pitch_quantize != PitchQuantize::Off {
+        ui.horizontal(|ui| {
+            ui.label("Glide between steps (ms)");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut config.pitch_glide_ms)
+                        .clamp_range(0.0..=2000.0)
+                        .speed(1.0),
+                )
+                .changed();
+        });
+    }
+    changed
+}
+
+/// Picker for an optional axis, "none" first.
+fn axis_picker(ui: &mut egui::Ui, id: &str, axis: &mut Option<Axis>) -> bool {
+    let mut changed = false;
+    let text = |axis: &Option<Axis>| axis.map_or("none".to_string(), |a| format!("{:?}", a));
+    egui::ComboBox::from_id_source(id)
+        .selected_text(text(axis))
+        .show_ui(ui, |ui| {
+            let choices = std::iter::once(None).chain(known_axes().into_iter().map(Some));
+            for choice in choices {
+                changed |= ui.selectable_value(axis, choice, text(&choice)).changed();
+            }
+        });
+    changed
+}
+
+/// Shape, target, depth and rate of the vibrato LFO. Returns true if edited.
+fn lfo_editor(ui: &mut egui::Ui, lfo: &mut Option<LfoConfig>) -> bool {
+    let mut changed = false;
+    let mut enabled = lfo.is_some();
+    if ui.checkbox(&mut enabled, "LFO").changed() {
+        *lfo = enabled.then(LfoConfig::default);
+        changed = true;
+    }
+    let Some(lfo) = lfo else {
+        return changed;
+    };
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source("lfo_shape")
+            .selected_text(format!("{:?}", lfo.shape))
+            .show_ui(ui, |ui| {
+                for shape in LfoShape::all() {
+                    changed |= ui
+                        .selectable_value(&mut lfo.shape, shape, format!("{:?}", shape))
+                        .changed();
+                }
+            });
+        let mut to_cc = matches!(lfo.target, LfoTarget::ControlChange { .. });
+        if ui.checkbox(&mut to_cc, "to CC").changed() {
+            lfo.target = if to_cc {
+                LfoTarget::ControlChange { controller: 1 }
+            } else {
+                LfoTarget::PitchBend
+            };
+            changed = true;
+        }
+        if let LfoTarget::ControlChange { controller } = &mut lfo.target {
+            changed |= ui
+                .add(egui::DragValue::new(controller).clamp_range(0..=127))
+                .changed();
+        }
+    });
+    ui.horizontal(|ui| {
+        let unit = match lfo.target {
+            LfoTarget::PitchBend => "Depth (semitones)",
+            LfoTarget::ControlChange { .. } => "Depth (CC steps)",
+        };
+        ui.label(unit);
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut lfo.depth)
+                    .clamp_range(0.0..=127.0)
+                    .speed(0.05),
+            )
+            .changed();
+        ui.label("by");
+        changed |= axis_picker(ui, "lfo_depth_axis", &mut lfo.depth_axis);
+    });
+    ui.horizontal(|ui| {
+        ui.label("Rate (Hz)");
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut lfo.rate_hz)
+                    .clamp_range(0.0..=50.0)
+                    .speed(0.05),
+            )
+            .changed();
+        if lfo.rate_axis.is_some() {
+            ui.label("to");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut lfo.max_rate_hz)
+                        .clamp_range(0.0..=50.0)
+                        .speed(0.05),
+                )
+                .changed();
+        }
+        ui.label("by");
+        changed |= axis_picker(ui, "lfo_rate_axis", &mut lfo.rate_axis);
+    });
+    ui.horizontal(|ui| {
+        let mut sync = lfo.sync_beats.is_some();
+        if ui.checkbox(&mut sync, "Sync to MIDI clock").changed() {
+            lfo.sync_beats = sync.then_some(1.0);
+            changed = true;
+        }
+        if let Some(beats) = &mut lfo.sync_beats {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(beats)
+                        .clamp_range(0.0625..=16.0)
+                        .speed(0.05)
+                        .suffix(" beats"),
+                )
+                .changed();
+        }
+    });
+    changed
+}
+
+fn melodic_editor(ui: &mut egui::Ui, melodic: &mut Option<MelodicConfig>) -> bool {
+    let mut changed = false;
+    let mut enabled = melodic.is_some();
+    if ui.checkbox(&mut enabled, "Melodic mode").changed() {
+        *melodic = enabled.then(MelodicConfig::default);
+        changed = true;
+    }
+    let Some(melodic) = melodic else {
+        return changed;
+    };
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_id_source("melodic_key")
+            .selected_text(NOTE_NAMES[(melodic.key % 12) as usize])
+            .width(50.0)
+            .show_ui(ui, |ui| {
+                for (i, name) in NOTE_NAMES.iter().enumerate() {
+                    changed |= ui
+                        .selectable_value(&mut melodic.key, i as u8, *name)
+                        .changed();
+                }
+            });
+        egui::ComboBox::from_id_source("melodic_scale")
+            .selected_text(format!("{:?}", melodic.scale))
+            .show_ui(ui, |ui| {
+                for scale in Scale::all() {
+                    changed |= ui
+                        .selectable_value(&mut melodic.scale, scale, format!("{:?}", scale))
+                        .changed();
+                }
+            });
+        ui.label("Octave");
+        changed |= ui
+            .add(egui::DragValue::new(&mut melodic.octave).clamp_range(-1..=9))
+            .changed();
+        ui.label("Velocity");
+        changed |= ui
+            .add(egui::DragValue::new(&mut melodic.velocity).clamp_range(1..=127))
+            .changed();
+    });
+    changed
+}
+
+fn rumble_editor(ui: &mut egui::Ui, rumble: &mut RumbleConfig) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        ui.label("Rumble at full bend (0 = off)");
+        changed |= ui
+            .add(
+                egui::DragValue::new(&mut rumble.bend_strength)
+                    .clamp_range(0.0..=1.0)
+                    .speed(0.01),
+            )
+            .changed();
+    });
+    ui.horizontal(|ui| {
+        let mut tick = rumble.step_tick.is_some();
+        if ui.checkbox(&mut tick, "Tick on quantized steps").changed() {
+            rumble.step_tick = tick.then_some(RumblePulse {
+                strength: 0.5,
+                duration_ms: 30,
+            });
+            changed = true;
+        }
+        if let Some(pulse) = &mut rumble.step_tick {
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut pulse.strength)
+                        .clamp_range(0.0..=1.0)
+                        .speed(0.01),
+                )
+                .changed();
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut pulse.duration_ms)
+                        .clamp_range(1..=1000)
+                        .suffix(" ms"),
+                )
+                .changed();
+        }
+    });
+    changed
+}
+
+fn touchpad_view(ui: &mut egui::Ui, fingers: &[Option<TouchPoint>]) {
+    // Roughly the DualShock 4 / DualSense pad's proportions
+    let (response, painter) = ui.allocate_painter(egui::vec2(240.0, 110.0), egui::Sense::hover());
+    let rect = response.rect;
+    painter.rect_stroke(rect, 4.0, ui.visuals().widgets.noninteractive.bg_stroke);
+    // Center lines, where absolute bindings are at rest
+    let stroke = egui::Stroke::new(1.0, egui::Color32::DARK_GRAY);
+    painter.line_segment([rect.center_top(), rect.center_bottom()], stroke);
+    painter.line_segment([rect.left_center(), rect.right_center()], stroke);
+    for (i, finger) in fingers.iter().enumerate() {
+        let Some((x, y)) = finger else {
+            continue;
+        };
+        let pos = egui::pos2(
+            rect.left() + x.clamp(0.0, 1.0) * rect.width(),
+            rect.top() + y.clamp(0.0, 1.0) * rect.height(),
+        );
+        painter.circle_filled(pos, 8.0, egui::Color32::LIGHT_BLUE);
+        painter.text(
+            pos,
+            egui::Align2::CENTER_CENTER,
+            (i + 1).to_string(),
+            egui::FontId::proportional(11.0),
+            egui::Color32::BLACK,
+        );
+    }
+}
+
+fn curve_plot(ui: &mut egui::Ui, curve: &mut ResponseCurve, input: Option<f32>) -> bool {
+    let (response, painter) = ui.allocate_painter(egui::vec2(200.0, 200.0), egui::Sense::hover());
+    let rect = response.rect;
+    let to_screen = |x: f32, y: f32| {
+        egui::pos2(
+            rect.left() + x * rect.width(),
+            rect.bottom() - y.clamp(0.0, 1.0) * rect.height(),
+        )
+    };
+
+    painter.rect_stroke(rect, 0.0, egui::Stroke::new(1.0, egui::Color32::DARK_GRAY));
+    painter.line_segment(
+        [to_screen(0.0, 0.0), to_screen(1.0, 1.0)],
+        egui::Stroke::new(1.0, egui::Color32::from_gray(60)),
+    );
+    let line = (0..=64)
+        .map(|i| {
+            let x = i as f32 / 64.0;
+            to_screen(x, curve.shape(x))
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        line,
+        egui::Stroke::new(2.0, egui::Color32::LIGHT_BLUE),
+    ));
+
+    let mut changed = false;
+    if let ResponseCurve::Breakpoints { points } = curve {
+        let last = points.len().saturating_sub(1);
+        let mut remove = None;
+        for i in 0..points.len() {
+            let center = to_screen(points[i][0], points[i][1]);
+            let handle = egui::Rect::from_center_size(center, egui::vec2(12.0, 12.0));
+            let r = ui.interact(handle, response.id.with(i), egui::Sense::click_and_drag());
+            if r.dragged() {
+                let delta = r.drag_delta();
+                // The end points stay pinned to the ends of the input range
+                if i != 0 && i != last {
+                    let (lo, hi) = (points[i - 1][0], points[i + 1][0]);
+                    points[i][0] = (points[i][0] + delta.x / rect.width()).clamp(lo, hi);
+                }
+                points[i][1] = (points[i][1] - delta.y / rect.height()).clamp(0.0, 1.0);
+                changed = true;
+            }
+            if r.secondary_clicked() && i != 0 && i != last {
+                remove = Some(i);
+            }
+            let color = if r.hovered() || r.dragged() {
+                egui::Color32::WHITE
+            } else {
+                egui::Color32::LIGHT_BLUE
+            };
+            painter.circle_filled(center, 4.0, color);
+        }
+        if let Some(i) = remove {
+            points.remove(i);
+            changed = true;
+        }
+    }
+
+    if let Some(x) = input {
+        let x = x.abs().clamp(0.0, 1.0);
+        painter.circle_filled(to_screen(x, curve.shape(x)), 5.0, egui::Color32::YELLOW);
+    }
+    changed
+}
+
 impl ControllerApp {
     pub fn new(
         controller_rx: mpsc::Receiver<ControllerEvent>,
         midi_tx: mpsc::Sender<ControllerEvent>,
+        command_tx: mpsc::Sender<ControllerCommand>,
+        max_bend_rate: Arc<AtomicU32>,
         midi_graph: Arc<MidiGraph>,
+        profile: Profile,
+        profile_path: Option<PathBuf>,
     ) -> Self {
         Self {
             controller_rx,
             midi_tx,
+            command_tx,
+            max_bend_rate,
             midi_graph,
-            last_pitch_bend: 8192,
-            last_tilt: 0.0,
-            button_states: HashMap::new(),
-            axis_states: HashMap::new(),
-            controller_name: None,
-            controller_mapping: None,
-            available_buttons: Vec::new(),
-            available_axes: Vec::new(),
+            profile,
+            profile_path,
+            devices: BTreeMap::new(),
+            detected: Vec::new(),
+            selected_device: None,
+            pitch_bends: HashMap::new(),
+            calibration: CalibrationStep::Idle,
             last_event_at: None,
+            keyboard: KeyboardSource::new(),
             endpoints: Vec::new(),
             selected_src: None,
             selected_dst: None,
@@ -98,6 +666,387 @@ impl ControllerApp {
         }
     }
 
+    /// Hand the edited profile to the controller thread.
+    fn push_profile(&self) {
+        let _ = self
+            .command_tx
+            .send(ControllerCommand::UpdateProfile(self.profile.clone()));
+    }
+
+    fn selected(&self) -> Option<&DeviceView> {
+        self.devices.get(&self.selected_device?)
+    }
+
+    fn select_device(&mut self, device: Option<DeviceId>) {
+        if self.selected_device != device {
+            self.selected_device = device;
+            self.calibration = CalibrationStep::Idle;
+        }
+    }
+
+    /// Picks which of the detected controllers get opened. The choice is saved to the
+    /// profile file right away, apart from any other unsaved edits.
+    fn device_picker(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.label("Devices");
+        let mut changed = false;
+
+        let mut all = self.profile.enabled_controllers.is_none();
+        if ui.checkbox(&mut all, "Open all controllers").changed() {
+            self.profile.enabled_controllers = if all {
+                None
+            } else {
+                // Start from what is open now so nothing gets dropped by surprise
+                Some(
+                    self.detected
+                        .iter()
+                        .filter(|d| d.open)
+                        .map(|d| EnabledController::Unit {
+                            guid: d.guid.clone(),
+                            slot: Some(d.slot),
+                        })
+                        .collect(),
+                )
+            };
+            changed = true;
+        }
+
+        if self.detected.is_empty() {
+            ui.label("No joysticks detected");
+        }
+        for device in &self.detected {
+            let label = format!("#{} {} (slot {})", device.index, device.name, device.slot);
+            if !device.is_game_controller {
+                ui.add_enabled(false, egui::Checkbox::new(&mut false, label))
+                    .on_disabled_hover_text("No game controller mapping for this joystick");
+                continue;
+            }
+            let mut enabled =
+                self.profile
+                    .wants_controller(&device.name, &device.guid, device.slot);
+            // With "Open all" ticked every controller is on; untick it to choose
+            let response = ui
+                .add_enabled(!all, egui::Checkbox::new(&mut enabled, label))
+                .on_hover_text(&device.guid);
+            if response.changed() {
+                if let Some(list) = &mut self.profile.enabled_controllers {
+                    set_enabled(list, &self.detected, device, enabled);
+                }
+                changed = true;
+            }
+        }
+        if changed {
+            self.save_enabled_controllers();
+        }
+        changed
+    }
+
+    /// Write `enabled_controllers` into the profile file, leaving the rest of it alone.
+    fn save_enabled_controllers(&mut self) {
+        let Some(path) = &self.profile_path else {
+            return;
+        };
+        let enabled = self.profile.enabled_controllers.as_deref();
+        if let Err(e) = save_enabled_controllers(path, enabled) {
+            self.status = Some(e.to_string());
+        }
+    }
+
+    /// Edits the selected controller's config (its own device profile if it has one,
+    /// else the default), or the default config when nothing is connected.
+    fn settings_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Settings");
+        let mut changed = false;
+
+        let (guid, slot) = match self.selected() {
+            Some(view) => {
+                let (guid, slot, name) = (view.guid.clone(), view.slot, view.name.clone());
+                ui.label(format!("Controller: {}", name));
+                let mut own = self.profile.device_profile(&guid, slot).is_some();
+                if ui
+                    .checkbox(&mut own, "Own profile for this controller")
+                    .changed()
+                {
+                    if own {
+                        self.profile.devices.push(DeviceProfile {
+                            guid: guid.clone(),
+                            slot: None,
+                            name,
+                            config: self.profile.default.clone(),
+                        });
+                    } else {
+                        self.profile.devices.retain(|d| !d.matches(&guid, slot));
+                    }
+                    changed = true;
+                }
+                if let Some(i) = self
+                    .profile
+                    .devices
+                    .iter()
+                    .position(|d| d.matches(&guid, slot))
+                {
+                    let device_profile = &mut self.profile.devices[i];
+                    let mut only_this = device_profile.slot.is_some();
+                    let label = format!("Only this unit (slot {})", slot);
+                    if ui.checkbox(&mut only_this, label).changed() {
+                        device_profile.slot = only_this.then_some(slot);
+                        changed = true;
+                    }
+                }
+                (guid, slot)
+            }
+            None => {
+                ui.label("No controller connected, editing the default profile.");
+                (String::new(), 0)
+            }
+        };
+
+        // Resolve live inputs before borrowing the config mutably
+        let calibrated: HashMap<Axis, i16> = self
+            .selected()
+            .map(|view| {
+                view.axes
+                    .iter()
+                    .filter_map(|axis| Some((*axis, view.calibrated_axis(&self.profile, *axis)?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let config = self.profile.config_for_mut(&guid, slot);
+
+        ui.horizontal(|ui| {
+            ui.label("MIDI channel (0-15)");
+            changed |= ui
+                .add(egui::DragValue::new(&mut config.channel).clamp_range(0..=15))
+                .changed();
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Pitch axis mode");
+            let mode = &mut config.pitch_axis_mode;
+            egui::ComboBox::from_id_source("pitch_axis_mode")
+                .selected_text(format!("{:?}", mode))
+                .show_ui(ui, |ui| {
+                    let modes = [
+                        PitchAxisMode::Bipolar,
+                        PitchAxisMode::Unipolar {
+                            direction: BendDirection::Up,
+                        },
+                        PitchAxisMode::Unipolar {
+                            direction: BendDirection::Down,
+                        },
+                        PitchAxisMode::Triggers,
+                    ];
+                    for choice in modes {
+                        changed |= ui
+                            .selectable_value(mode, choice, format!("{:?}", choice))
+                            .changed();
+                    }
+                });
+        });
+        ui.label(format!("Pitch curve ({:?})", config.pitch_axis));
+        let input = (!calibrated.is_empty()).then(|| pitch_input(config, &calibrated));
+        changed |= curve_editor(ui, "pitch_curve", &mut config.pitch_curve, input);
+
+        ui.label("Pitch smoothing (0 = off)");
+        ui.horizontal(|ui| {
+            ui.label("Time constant (ms)");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut config.pitch_smoothing.time_constant_ms)
+                        .clamp_range(0.0..=1000.0)
+                        .speed(1.0),
+                )
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label("Max change per ms");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut config.pitch_smoothing.max_slew_per_ms)
+                        .clamp_range(0.0..=16383.0)
+                        .speed(1.0),
+                )
+                .changed();
+        });
+
+        ui.separator();
+        changed |= quantize_editor(ui, config);
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Release to center (ms, 0 = off)");
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut config.pitch_release.time_ms)
+                        .clamp_range(0.0..=5000.0)
+                        .speed(1.0),
+                )
+                .changed();
+        });
+        if config.pitch_release.time_ms > 0.0 {
+            ui.label("Release shape (time vs. way back)");
+            changed |= curve_editor(ui, "release_curve", &mut config.pitch_release.curve, None);
+        }
+
+        ui.separator();
+        changed |= lfo_editor(ui, &mut config.lfo);
+
+        ui.separator();
+        changed |= melodic_editor(ui, &mut config.melodic);
+
+        ui.separator();
+        changed |= rumble_editor(ui, &mut config.rumble);
+
+        for (i, binding) in config.axes.iter_mut().enumerate() {
+            let input = calibrated.get(&binding.axis).map(|raw| {
+                let norm = match binding.polarity() {
+                    AxisPolarity::Bipolar => normalize_axis(*raw, binding.invert),
+                    AxisPolarity::Unipolar => normalize_unipolar(*raw),
+                };
+                apply_deadzone(norm, (binding.deadzone as f32) / 32767.0)
+            });
+            ui.separator();
+            ui.label(format!(
+                "{:?} → CC {} curve",
+                binding.axis, binding.controller
+            ));
+            changed |= curve_editor(ui, &format!("axis_curve_{}", i), &mut binding.curve, input);
+        }
+
+        ui.separator();
+        changed |= self.calibration_wizard(ui);
+
+        ui.separator();
+        changed |= self.device_picker(ui);
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Max pitch bends/s per channel (0 = no cap)");
+            let response =
+                ui.add(egui::DragValue::new(&mut self.profile.max_bend_rate).clamp_range(0..=1000));
+            if response.changed() {
+                self.max_bend_rate
+                    .store(self.profile.max_bend_rate, Ordering::Relaxed);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Computer keyboard plays");
+            let mode = &mut self.profile.keyboard.mode;
+            egui::ComboBox::from_id_source("keyboard_mode")
+                .selected_text(format!("{:?}", mode))
+                .show_ui(ui, |ui| {
+                    for option in [
+                        KeyboardMode::Fallback,
+                        KeyboardMode::Always,
+                        KeyboardMode::Off,
+                    ] {
+                        changed |= ui
+                            .selectable_value(mode, option, format!("{:?}", option))
+                            .changed();
+                    }
+                });
+        });
+
+        if changed {
+            self.push_profile();
+        }
+    }
+
+    /// Returns true if a calibration was stored or cleared.
+    fn calibration_wizard(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.label("Calibration");
+        let Some(view) = self.selected() else {
+            ui.label("Connect a controller to calibrate it.");
+            return false;
+        };
+        let (guid, slot, name) = (view.guid.clone(), view.slot, view.name.clone());
+        let axes = view.axes.clone();
+        let current = view.axis_states.clone();
+
+        let mut changed = false;
+        match &self.calibration {
+            CalibrationStep::Idle => {
+                // A GUID-wide calibration (no slot) is shared with other units, so it
+                // is only reported here; Clear removes this unit's own one.
+                let stored = self
+                    .profile
+                    .calibration_for(&guid, slot)
+                    .map(|c| c.slot.is_some());
+                ui.label(match stored {
+                    Some(true) => "This controller is calibrated.",
+                    Some(false) => {
+                        "This controller uses the calibration stored for every controller of its kind."
+                    }
+                    None => "This controller is not calibrated.",
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("Calibrate").clicked() {
+                        self.calibration = CalibrationStep::Rest;
+                    }
+                    if stored == Some(true) && ui.button("Clear").clicked() {
+                        self.profile.clear_calibration(&guid, slot);
+                        changed = true;
+                    }
+                });
+            }
+            CalibrationStep::Rest => {
+                ui.label("1. Let go of both sticks and triggers, then press Next.");
+                let mut next = false;
+                ui.horizontal(|ui| {
+                    next = ui.button("Next").clicked();
+                    if ui.button("Cancel").clicked() {
+                        self.calibration = CalibrationStep::Idle;
+                    }
+                });
+                if next {
+                    let rest = axes
+                        .iter()
+                        .map(|axis| (*axis, current.get(axis).copied().unwrap_or(0)))
+                        .collect();
+                    self.calibration = CalibrationStep::Extremes(CalibrationRecorder::new(rest));
+                }
+            }
+            CalibrationStep::Extremes(recorder) => {
+                ui.label("2. Move each stick around its full circle and press both triggers fully, then press Finish.");
+                for axis in &axes {
+                    if let Some((min, max)) = recorder.range(*axis) {
+                        ui.label(format!("{:?}: {} .. {}", axis, min, max));
+                    }
+                }
+                let mut finish = false;
+                let mut cancel = false;
+                ui.horizontal(|ui| {
+                    finish = ui.button("Finish").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+                if finish {
+                    let calibration = recorder.finish(&guid, slot, &name, &axes);
+                    self.status = Some(format!(
+                        "Calibrated {} axes; save the profile to keep it",
+                        calibration.axes.len()
+                    ));
+                    self.profile.set_calibration(calibration);
+                    changed = true;
+                }
+                if finish || cancel {
+                    self.calibration = CalibrationStep::Idle;
+                }
+            }
+        }
+        changed
+    }
+
+    fn save_profile(&mut self) {
+        let Some(path) = &self.profile_path else {
+            self.status = Some("No profile path available".to_string());
+            return;
+        };
+        self.status = Some(match save_profile(path, &self.profile) {
+            Ok(()) => format!("Saved profile to {}", path.display()),
+            Err(e) => e.to_string(),
+        });
+    }
+
     fn selected_pair(&self) -> Option<(MidiEndpointId, MidiEndpointId)> {
         let src_idx = self.selected_src?;
         let dst_idx = self.selected_dst?;
@@ -106,38 +1055,195 @@ impl ControllerApp {
         Some((src.id.clone(), dst.id.clone()))
     }
 
+    fn keyboard_active(&self) -> bool {
+        match self.profile.keyboard.mode {
+            KeyboardMode::Always => true,
+            KeyboardMode::Fallback => self.devices.is_empty(),
+            KeyboardMode::Off => false,
+        }
+    }
+
+    /// Play this frame's key presses, or silence the keyboard once a controller
+    /// takes over.
+    fn keyboard_input(&mut self, ctx: &egui::Context) {
+        if !self.keyboard_active() {
+            for event in self.keyboard.release_all() {
+                self.handle_event(event);
+            }
+            return;
+        }
+        // Typing into a settings field shouldn't play, but releases always count
+        let typing = ctx.wants_keyboard_input();
+        let keys: Vec<_> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Key {
+                        key,
+                        pressed,
+                        repeat: false,
+                        ..
+                    } if !(typing && *pressed) => Some((*key, *pressed)),
+                    _ => None,
+                })
+                .collect()
+        });
+        for (key, pressed) in keys {
+            for event in self.keyboard.key(&self.profile.keyboard, key, pressed) {
+                self.handle_event(event);
+            }
+        }
+    }
+
+    /// Octave display and the pad whose drag and wheel bend the keyboard's notes.
+    fn keyboard_pad(&mut self, ui: &mut egui::Ui) {
+        let config = &self.profile.keyboard;
+        ui.separator();
+        ui.heading("Keyboard");
+        ui.label(format!(
+            "ch {}, octave {:+} ({} / {} to shift)",
+            config.channel,
+            self.keyboard.octave(),
+            config.octave_down,
+            config.octave_up
+        ));
+        ui.label("Drag up/down or scroll on the pad to bend, middle-click to recenter");
+        let (response, painter) =
+            ui.allocate_painter(egui::vec2(240.0, 80.0), egui::Sense::click_and_drag());
+        let rect = response.rect;
+        painter.rect_stroke(rect, 4.0, ui.visuals().widgets.noninteractive.bg_stroke);
+        let y = rect.center().y - self.keyboard.bend() * rect.height() / 2.0;
+        painter.line_segment(
+            [egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)],
+            egui::Stroke::new(2.0, egui::Color32::YELLOW),
+        );
+
+        let mut events = Vec::new();
+        if response.dragged_by(egui::PointerButton::Primary) {
+            let dy = ui.input(|i| Some(i.pointer.interact_pos()?.y - i.pointer.press_origin()?.y));
+            events.extend(self.keyboard.drag(config, dy));
+        } else if response.drag_released() {
+            events.extend(self.keyboard.drag(config, None));
+        }
+        if response.hovered() {
+            let dy = ui.input(|i| i.raw_scroll_delta.y);
+            if dy != 0.0 {
+                events.extend(self.keyboard.scroll(config, dy));
+            }
+        }
+        if response.middle_clicked() {
+            events.extend(self.keyboard.recenter(config));
+        }
+        for event in events {
+            self.handle_event(event);
+        }
+    }
+
     fn handle_event(&mut self, event: ControllerEvent) {
         match event {
-            ControllerEvent::ButtonDown => {
-                let _ = self.midi_tx.send(ControllerEvent::ButtonDown);
+            ControllerEvent::NoteOn { .. }
+            | ControllerEvent::NoteOff { .. }
+            | ControllerEvent::ControlChange { .. }
+            | ControllerEvent::ProgramChange { .. }
+            | ControllerEvent::Lfo { .. } => {
+                let _ = self.midi_tx.send(event);
             }
-            ControllerEvent::ButtonUp => {
-                let _ = self.midi_tx.send(ControllerEvent::ButtonUp);
+            ControllerEvent::PitchBend { channel, value } => {
+                self.pitch_bends.insert(channel, value);
+                let _ = self.midi_tx.send(event);
             }
-            ControllerEvent::PitchBend(value) => {
-                self.last_pitch_bend = value;
-                // Convert 0..16383 (center 8192) to -1.0..1.0
-                let tilt = (value as f32 - 8192.0) / 8192.0;
-                self.last_tilt = tilt.clamp(-1.0, 1.0);
-                let _ = self.midi_tx.send(ControllerEvent::PitchBend(value));
+            ControllerEvent::RawButton {
+                device,
+                button,
+                pressed,
+            } => {
+                if let Some(view) = self.devices.get_mut(&device) {
+                    view.button_states.insert(button, pressed);
+                }
+            }
+            ControllerEvent::RawAxis {
+                device,
+                axis,
+                value,
+            } => {
+                if let Some(view) = self.devices.get_mut(&device) {
+                    view.axis_states.insert(axis, value);
+                }
+                if self.selected_device == Some(device) {
+                    if let CalibrationStep::Extremes(recorder) = &mut self.calibration {
+                        recorder.record(axis, value);
+                    }
+                }
+            }
+            ControllerEvent::RawMotion {
+                device,
+                source,
+                degrees,
+            } => {
+                if let Some(view) = self.devices.get_mut(&device) {
+                    view.motion_states.insert(source, degrees);
+                }
             }
-            ControllerEvent::RawButton { button, pressed } => {
-                self.button_states.insert(button, pressed);
+            ControllerEvent::RawTouch { device, fingers } => {
+                if let Some(view) = self.devices.get_mut(&device) {
+                    view.touch = fingers;
+                }
             }
-            ControllerEvent::RawAxis { axis, value } => {
-                self.axis_states.insert(axis, value);
+            ControllerEvent::BendHold { device, held } => {
+                if let Some(view) = self.devices.get_mut(&device) {
+                    view.bend_held = held;
+                }
+            }
+            ControllerEvent::Layer { device, layer } => {
+                if let Some(view) = self.devices.get_mut(&device) {
+                    view.layer = layer;
+                }
+            }
+            ControllerEvent::Melodic { device, shift } => {
+                if let Some(view) = self.devices.get_mut(&device) {
+                    view.melodic = shift;
+                }
             }
             ControllerEvent::ControllerInfo {
+                device,
                 name,
+                guid,
+                slot,
                 mapping,
                 buttons,
                 axes,
             } => {
-                self.controller_name = Some(name);
-                self.controller_mapping = Some(mapping);
-                self.available_buttons = buttons;
-                self.available_axes = axes;
+                self.devices.insert(
+                    device,
+                    DeviceView {
+                        name,
+                        guid,
+                        slot,
+                        mapping,
+                        buttons,
+                        axes,
+                        button_states: HashMap::new(),
+                        axis_states: HashMap::new(),
+                        motion_states: BTreeMap::new(),
+                        touch: Vec::new(),
+                        bend_held: false,
+                        layer: None,
+                        melodic: MelodicShift::default(),
+                    },
+                );
+                if self.selected_device.is_none() {
+                    self.select_device(Some(device));
+                }
+            }
+            ControllerEvent::ControllerDisconnected { device } => {
+                if let Some(view) = self.devices.remove(&device) {
+                    self.status = Some(format!("{} disconnected", view.name));
+                }
+                if self.selected_device == Some(device) {
+                    self.select_device(self.devices.keys().next().copied());
+                }
             }
+            ControllerEvent::DeviceList(detected) => self.detected = detected,
         }
 
         self.last_event_at = Some(Instant::now());
@@ -151,14 +1257,49 @@ impl eframe::App for ControllerApp {
         while let Ok(event) = self.controller_rx.try_recv() {
             self.handle_event(event);
         }
+        self.keyboard_input(ctx);
+
+        egui::SidePanel::right("settings").show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| self.settings_panel(ui));
+        });
 
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Pitch Controller Monitor");
-            if let Some(name) = &self.controller_name {
-                ui.label(format!("Controller: {}", name));
+            if self.devices.is_empty() {
+                ui.label("Controller: not connected");
+            }
+            let mut clicked = None;
+            for (device, view) in &self.devices {
+                let channel = view.config(&self.profile).channel;
+                let bend = self.pitch_bends.get(&channel).copied().unwrap_or(8192);
+                // Convert 0..16383 (center 8192) to -1.0..1.0
+                let tilt = ((bend as f32 - 8192.0) / 8192.0).clamp(-1.0, 1.0);
+                ui.horizontal(|ui| {
+                    let selected = self.selected_device == Some(*device);
+                    let label = format!("{} (ch {})", view.name, channel);
+                    if ui.selectable_label(selected, label).clicked() {
+                        clicked = Some(*device);
+                    }
+                    ui.add(
+                        egui::ProgressBar::new((tilt + 1.0) / 2.0)
+                            .desired_width(160.0)
+                            .text(format!("{:+.2}", tilt)),
+                    );
+                    ui.label(format!("bend: {} (0-16383)", bend));
+                    if view.bend_held {
+                        ui.colored_label(egui::Color32::YELLOW, "HOLD");
+                    }
+                    let layers = &view.config(&self.profile).layers;
+                    if let Some(layer) = view.layer.and_then(|i| layers.get(i)) {
+                        ui.colored_label(egui::Color32::LIGHT_BLUE, layer.to_string());
+                    }
+                });
+            }
+            if clicked.is_some() {
+                self.select_device(clicked);
             }
-            if let Some(mapping) = &self.controller_mapping {
-                ui.label(format!("Mapping: {}", mapping));
+            if self.keyboard_active() {
+                self.keyboard_pad(ui);
             }
             if let Some(last) = self.last_event_at {
                 let ago = last.elapsed().as_millis();
@@ -167,43 +1308,126 @@ impl eframe::App for ControllerApp {
                 ui.label("Waiting for controller input...");
             }
 
-            ui.separator();
-            ui.label("Pitch axis (mapped)");
-            let progress = (self.last_tilt + 1.0) / 2.0; // map -1..1 to 0..1
-            ui.add(egui::ProgressBar::new(progress).text(format!("{:+.2}", self.last_tilt)));
-            ui.label(format!("Pitch bend value: {} (0-16383)", self.last_pitch_bend));
+            ui.horizontal(|ui| {
+                match &self.profile_path {
+                    Some(path) => ui.label(format!("Profile: {}", path.display())),
+                    None => ui.label("Profile: (not persisted)"),
+                };
+                if ui.button("Save profile").clicked() {
+                    self.save_profile();
+                }
+            });
 
-            ui.separator();
-            ui.heading("Buttons");
-            if self.available_buttons.is_empty() {
-                ui.label("No buttons detected yet.");
-            } else {
-                for b in &self.available_buttons {
-                    let pressed = self.button_states.get(b).copied().unwrap_or(false);
-                    ui.horizontal(|ui| {
-                        ui.label(format!("{:?}", b));
-                        ui.colored_label(
-                            if pressed { egui::Color32::LIGHT_GREEN } else { egui::Color32::GRAY },
-                            if pressed { "pressed" } else { "released" },
-                        );
+            if let Some(view) = self.selected() {
+                let config = view.config(&self.profile);
+
+                ui.separator();
+                ui.label(format!("Mapping: {}", view.mapping));
+                ui.label(format!("GUID: {} (slot {})", view.guid, view.slot));
+
+                if let Some(melodic) = &config.melodic {
+                    let shift = view.melodic;
+                    ui.separator();
+                    ui.heading("Melodic");
+                    ui.label(format!(
+                        "Root {} {:?} (octave {:+}, transpose {:+})",
+                        note_name(melodic.root(shift)),
+                        melodic.scale,
+                        shift.octave,
+                        shift.transpose
+                    ));
+                    ui.horizontal_wrapped(|ui| {
+                        for (degree, button) in melodic.degrees.iter().enumerate() {
+                            let note = melodic.note(degree, shift);
+                            let name = note.map_or("-".to_string(), |n| note_name(n as i32));
+                            ui.label(format!("{:?}: {}", button, name));
+                        }
                     });
                 }
-            }
 
-            ui.separator();
-            ui.heading("Axes");
-            if self.available_axes.is_empty() {
-                ui.label("No axes detected yet.");
-            } else {
-                for axis in &self.available_axes {
-                    let raw = self.axis_states.get(axis).copied().unwrap_or(0);
-                    let norm = (raw as f32 / 32767.0).clamp(-1.0, 1.0);
-                    let progress = (norm + 1.0) / 2.0;
-                    ui.horizontal(|ui| {
-                        ui.label(format!("{:?}", axis));
-                        ui.add(egui::ProgressBar::new(progress).text(format!("{:+.2}", norm)));
-                        ui.label(format!("raw: {}", raw));
-                    });
+                ui.separator();
+                ui.heading("Buttons");
+                if view.buttons.is_empty() {
+                    ui.label("No buttons detected yet.");
+                } else {
+                    for b in &view.buttons {
+                        let pressed = view.button_states.get(b).copied().unwrap_or(false);
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{:?}", b));
+                            ui.colored_label(
+                                if pressed {
+                                    egui::Color32::LIGHT_GREEN
+                                } else {
+                                    egui::Color32::GRAY
+                                },
+                                if pressed { "pressed" } else { "released" },
+                            );
+                            if let Some(layer) = config.layers.iter().find(|l| l.modifier == *b) {
+                                ui.label(format!("→ {} ({:?})", layer, layer.mode));
+                            }
+                            for binding in config.button_bindings(view.layer, *b) {
+                                let mut label = format!("→ {}", binding.action);
+                                if let Some(source) = &binding.velocity_source {
+                                    label.push_str(&format!(" (vel: {})", source));
+                                }
+                                ui.label(label);
+                            }
+                        });
+                    }
+                }
+
+                ui.separator();
+                ui.heading("Axes");
+                if view.axes.is_empty() {
+                    ui.label("No axes detected yet.");
+                } else {
+                    for axis in &view.axes {
+                        let raw = view.axis_states.get(axis).copied().unwrap_or(0);
+                        let value = view.calibrated_axis(&self.profile, *axis).unwrap_or(raw);
+                        let norm = (value as f32 / 32767.0).clamp(-1.0, 1.0);
+                        let progress = (norm + 1.0) / 2.0;
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{:?}", axis));
+                            ui.add(egui::ProgressBar::new(progress).text(format!("{:+.2}", norm)));
+                            ui.label(format!("raw: {}", raw));
+                            if config.is_pitch_axis(*axis) {
+                                ui.label("→ pitch bend");
+                            }
+                            for binding in config.axis_bindings(view.layer, *axis) {
+                                ui.label(format!("→ {}", binding));
+                            }
+                        });
+                    }
+                    for binding in &config.sticks {
+                        ui.label(binding.to_string());
+                    }
+                }
+
+                if !view.motion_states.is_empty() {
+                    ui.separator();
+                    ui.heading("Motion");
+                    for (source, degrees) in &view.motion_states {
+                        // Show ±90° across the bar
+                        let progress = ((degrees / 90.0).clamp(-1.0, 1.0) + 1.0) / 2.0;
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{:?}", source));
+                            ui.add(
+                                egui::ProgressBar::new(progress).text(format!("{:+.0}°", degrees)),
+                            );
+                            for binding in config.motion.iter().filter(|m| m.source == *source) {
+                                ui.label(format!("→ {}", binding.target));
+                            }
+                        });
+                    }
+                }
+
+                if !view.touch.is_empty() {
+                    ui.separator();
+                    ui.heading("Touchpad");
+                    touchpad_view(ui, &view.touch);
+                    for binding in &config.touchpad {
+                        ui.label(binding.to_string());
+                    }
                 }
             }
 
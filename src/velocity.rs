@@ -0,0 +1,220 @@
+use crate::controller::normalize_axis;
+use crate::curve::ResponseCurve;
+use crate::mapping::Stick;
+use sdl2::controller::Axis;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// How far back stick movement counts towards the speed at a press.
+const SPEED_WINDOW: Duration = Duration::from_millis(60);
+
+/// Where a note's velocity comes from, instead of the binding's fixed `velocity`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum VelocitySource {
+    /// How far an axis (usually a trigger) is pushed when the button goes down.
+    Axis {
+        #[serde(with = "crate::profile::axis_name")]
+        axis: Axis,
+        #[serde(flatten)]
+        range: VelocityRange,
+    },
+    /// How fast a stick was moving just before the press.
+    StickSpeed {
+        stick: Stick,
+        /// Speed for `max`, in full throws (center to edge) per second.
+        #[serde(default = "default_full_speed")]
+        full_speed: f32,
+        #[serde(flatten)]
+        range: VelocityRange,
+    },
+}
+
+impl fmt::Display for VelocitySource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VelocitySource::Axis { axis, .. } => write!(f, "{:?}", axis),
+            VelocitySource::StickSpeed { stick, .. } => write!(f, "{:?} stick speed", stick),
+        }
+    }
+}
+
+fn default_full_speed() -> f32 {
+    10.0
+}
+
+/// Scaling of a velocity source's `0.0..=1.0` amount into `min..=max`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VelocityRange {
+    #[serde(default = "default_min")]
+    pub min: u8,
+    #[serde(default = "default_max")]
+    pub max: u8,
+    #[serde(default)]
+    pub curve: ResponseCurve,
+}
+
+fn default_min() -> u8 {
+    1
+}
+
+fn default_max() -> u8 {
+    127
+}
+
+impl VelocityRange {
+    fn velocity(&self, amount: f32) -> u8 {
+        let t = self.curve.shape(amount.clamp(0.0, 1.0));
+        let v = self.min as f32 + (self.max as f32 - self.min as f32) * t;
+        // Velocity 0 would be a note off
+        v.round().clamp(1.0, 127.0) as u8
+    }
+}
+
+/// Recent axis values of one controller, for velocity sources to read at press time.
+#[derive(Debug, Default)]
+pub struct VelocityTracker {
+    axes: HashMap<Axis, i16>,
+    /// Stick positions of the last `SPEED_WINDOW`, oldest first.
+    sticks: HashMap<Stick, VecDeque<(Instant, f32, f32)>>,
+}
+
+impl VelocityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a (calibrated) axis value.
+    pub fn axis(&mut self, axis: Axis, value: i16, now: Instant) {
+        self.axes.insert(axis, value);
+        for stick in [Stick::Left, Stick::Right] {
+            if !stick.has(axis) {
+                continue;
+            }
+            let (x, y) = stick.axes();
+            let position = |axis| {
+                self.axes
+                    .get(&axis)
+                    .map_or(0.0, |v| normalize_axis(*v, false))
+            };
+            let sample = (now, position(x), position(y));
+            let samples = self.sticks.entry(stick).or_default();
+            samples.retain(|(at, _, _)| now.duration_since(*at) <= SPEED_WINDOW);
+            samples.push_back(sample);
+        }
+    }
+
+    /// Velocity from `source` at `now`.
+    pub fn velocity(&self, source: &VelocitySource, now: Instant) -> u8 {
+        match source {
+            VelocitySource::Axis { axis, range } => {
+                let value = self.axes.get(axis).copied().unwrap_or(0);
+                range.velocity(normalize_axis(value, false).abs())
+            }
+            VelocitySource::StickSpeed {
+                stick,
+                full_speed,
+                range,
+            } => {
+                if *full_speed <= 0.0 {
+                    return range.velocity(1.0);
+                }
+                range.velocity(self.stick_speed(*stick, now) / full_speed)
+            }
+        }
+    }
+
+    /// Distance the stick travelled over the last `SPEED_WINDOW`, per second.
+    fn stick_speed(&self, stick: Stick, now: Instant) -> f32 {
+        let Some(samples) = self.sticks.get(&stick) else {
+            return 0.0;
+        };
+        let recent: Vec<_> = samples
+            .iter()
+            .filter(|(at, _, _)| now.duration_since(*at) <= SPEED_WINDOW)
+            .collect();
+        let distance: f32 = recent
+            .windows(2)
+            .map(|pair| {
+                let (_, x0, y0) = pair[0];
+                let (_, x1, y1) = pair[1];
+                (x1 - x0).hypot(y1 - y0)
+            })
+            .sum();
+        distance / SPEED_WINDOW.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(min: u8, max: u8) -> VelocityRange {
+        VelocityRange {
+            min,
+            max,
+            curve: ResponseCurve::Linear,
+        }
+    }
+
+    #[test]
+    fn amount_scales_into_the_range() {
+        let r = range(1, 127);
+        assert_eq!(r.velocity(0.0), 1);
+        assert_eq!(r.velocity(0.5), 64);
+        assert_eq!(r.velocity(1.0), 127);
+        assert_eq!(r.velocity(3.0), 127);
+        assert_eq!(range(40, 80).velocity(0.5), 60);
+    }
+
+    #[test]
+    fn velocity_never_turns_into_a_note_off() {
+        assert_eq!(range(0, 127).velocity(0.0), 1);
+        assert_eq!(range(0, 0).velocity(1.0), 1);
+    }
+
+    #[test]
+    fn the_curve_shapes_the_amount() {
+        let r = VelocityRange {
+            curve: ResponseCurve::Exponential { amount: 1.0 },
+            ..range(0, 100)
+        };
+        // 0.5 to the fourth power
+        assert_eq!(r.velocity(0.5), 6);
+    }
+
+    #[test]
+    fn an_axis_source_reads_how_far_it_is_pushed() {
+        let now = Instant::now();
+        let mut tracker = VelocityTracker::new();
+        let source = VelocitySource::Axis {
+            axis: Axis::TriggerRight,
+            range: range(1, 127),
+        };
+        assert_eq!(tracker.velocity(&source, now), 1);
+        tracker.axis(Axis::TriggerRight, 32767, now);
+        assert_eq!(tracker.velocity(&source, now), 127);
+    }
+
+    #[test]
+    fn stick_speed_counts_recent_movement_only() {
+        let start = Instant::now();
+        let mut tracker = VelocityTracker::new();
+        let source = VelocitySource::StickSpeed {
+            stick: Stick::Left,
+            full_speed: 10.0,
+            range: range(1, 127),
+        };
+        assert_eq!(tracker.velocity(&source, start), 1);
+        // Center to the edge within the window is a full throw in 60 ms
+        tracker.axis(Axis::LeftX, 0, start);
+        tracker.axis(Axis::LeftX, 32767, start + Duration::from_millis(30));
+        let pressed = start + Duration::from_millis(40);
+        let expected = range(1, 127).velocity(1.0 / SPEED_WINDOW.as_secs_f32() / 10.0);
+        assert_eq!(tracker.velocity(&source, pressed), expected);
+        // Long after the flick the stick is still
+        assert_eq!(tracker.velocity(&source, start + Duration::from_secs(1)), 1);
+    }
+}